@@ -0,0 +1,317 @@
+//! `#[derive(HatterObject)]` and `#[hatter_methods]` expose your Rust
+//! structs to Hatter templates without hand-writing `impl Object`.
+//! Enable hatter's `derive` feature to use them:
+//!
+//! ```ignore
+//! use hatter::{hatter_methods, HatterObject};
+//!
+//! #[derive(HatterObject)]
+//! #[hatter(methods)]
+//! struct User {
+//!     name: String,
+//!     #[hatter(skip)]
+//!     password: String,
+//! }
+//!
+//! #[hatter_methods]
+//! impl User {
+//!     #[hatter(method)]
+//!     fn greet(&self, greeting: String) -> String {
+//!         format!("{}, {}!", greeting, self.name)
+//!     }
+//! }
+//! ```
+//!
+//! Then `user.name` and `user.greet("Hiya")` work in templates.
+//!
+//! Struct attributes:
+//! - `#[hatter(name = "Person")]` sets the `typename()`.
+//! - `#[hatter(methods)]` exposes the `#[hatter(method)]` functions
+//!   in the struct's `#[hatter_methods]` impl block.
+//!
+//! Field attributes:
+//! - `#[hatter(skip)]` hides a field.
+//! - `#[hatter(rename = "full-name")]` uses another name in Hatter.
+//!
+//! Fields are read with `Into<Value>`. Since `Object::set()` takes
+//! `&self`, only `Cell` and `RefCell` fields can be set from Hatter,
+//! using `TryFrom<Value>`. Values of the wrong type are ignored.
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::TokenStream as TokenStream2,
+    quote::quote,
+    syn::{
+        parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Error, Fields, FnArg,
+        ImplItem, ItemImpl, LitStr, Result, ReturnType, Type,
+    },
+};
+
+/// Implement `hatter::Object` for a struct with named fields.
+#[proc_macro_derive(HatterObject, attributes(hatter))]
+pub fn derive_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    object(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Expose the `#[hatter(method)]` functions in an impl block to
+/// Hatter. The struct must also have `#[hatter(methods)]`.
+#[proc_macro_attribute]
+pub fn hatter_methods(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemImpl);
+    methods(item)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Generate `impl Object`.
+fn object(input: DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut typename = ident.to_string();
+    let mut has_methods = false;
+    for attr in hatter_attrs(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                typename = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("methods") {
+                has_methods = true;
+            } else {
+                return Err(meta.error("expected `name` or `methods`"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let fields = match &input.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(Error::new_spanned(
+                ident,
+                "HatterObject can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut getters = vec![];
+    let mut setters = vec![];
+    for field in fields {
+        let name = field.ident.as_ref().unwrap();
+        let mut key = name.to_string();
+        let mut skip = false;
+        for attr in hatter_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("rename") {
+                    key = meta.value()?.parse::<LitStr>()?.value();
+                } else {
+                    return Err(meta.error("expected `skip` or `rename`"));
+                }
+                Ok(())
+            })?;
+        }
+        if skip {
+            continue;
+        }
+
+        match cell_kind(&field.ty).as_deref() {
+            Some("Cell") => {
+                getters.push(quote!(#key => Some(self.#name.get().into()),));
+                setters.push(quote! {
+                    #key => {
+                        if let Ok(val) = ::std::convert::TryFrom::try_from(val) {
+                            self.#name.set(val);
+                        }
+                    }
+                });
+            }
+            Some("RefCell") => {
+                getters.push(quote!(#key => Some(self.#name.borrow().clone().into()),));
+                setters.push(quote! {
+                    #key => {
+                        if let Ok(val) = ::std::convert::TryFrom::try_from(val) {
+                            *self.#name.borrow_mut() = val;
+                        }
+                    }
+                });
+            }
+            _ => getters.push(quote!(#key => Some(self.#name.clone().into()),)),
+        }
+    }
+
+    let call = if has_methods {
+        quote! {
+            fn call(&self, method: &str, args: ::hatter::Args) -> ::hatter::Result<::hatter::Value> {
+                self.__hatter_call(method, args)
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::hatter::Object for #ident #ty_generics #where_clause {
+            fn typename(&self) -> &str {
+                #typename
+            }
+
+            fn get(&self, key: &str) -> Option<::hatter::Value> {
+                match key {
+                    #(#getters)*
+                    _ => None,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn set(&self, key: &str, val: ::hatter::Value) {
+                match key {
+                    #(#setters)*
+                    _ => {}
+                }
+            }
+
+            #call
+        }
+    })
+}
+
+/// Generate a hidden `__hatter_call()` that calls the right
+/// `#[hatter(method)]`, for `Object::call()`.
+fn methods(mut item: ItemImpl) -> Result<TokenStream2> {
+    let mut arms = vec![];
+    for impl_item in &mut item.items {
+        let method = match impl_item {
+            ImplItem::Fn(method) => method,
+            _ => continue,
+        };
+
+        let mut is_method = false;
+        for attr in hatter_attrs(&method.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("method") {
+                    is_method = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `method`"))
+                }
+            })?;
+        }
+        if !is_method {
+            continue;
+        }
+        method.attrs.retain(|attr| !attr.path().is_ident("hatter"));
+
+        let sig = &method.sig;
+        match sig.receiver() {
+            Some(recv) if recv.reference.is_some() && recv.mutability.is_none() => {}
+            _ => {
+                return Err(Error::new_spanned(
+                    sig,
+                    "#[hatter(method)] functions must take &self",
+                ))
+            }
+        }
+
+        let name = &sig.ident;
+        let key = name.to_string();
+        let args = sig
+            .inputs
+            .iter()
+            .filter(|arg| matches!(arg, FnArg::Typed(..)))
+            .enumerate()
+            .map(|(i, _)| quote!(::std::convert::TryFrom::try_from(args.need(#i)?)?))
+            .collect::<Vec<_>>();
+        let arity = args.len();
+        let call = if returns_result(&sig.output) {
+            quote!(self.#name(#(#args),*)?)
+        } else {
+            quote!(self.#name(#(#args),*))
+        };
+
+        arms.push(quote! {
+            #key => {
+                if args.len() != #arity {
+                    return Err(hatter_error(format!(
+                        "expected {} args, got {}",
+                        #arity,
+                        args.len()
+                    )));
+                }
+                Ok(::hatter::Value::from(#call))
+            }
+        });
+    }
+
+    let self_ty = &item.self_ty;
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        #item
+
+        impl #impl_generics #self_ty #where_clause {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            pub fn __hatter_call(
+                &self,
+                method: &str,
+                mut args: ::hatter::Args,
+            ) -> ::hatter::Result<::hatter::Value> {
+                fn hatter_error(details: String) -> ::hatter::Error {
+                    ::hatter::Error::new(::hatter::ErrorKind::RuntimeError, details, 0, 0)
+                }
+                match method {
+                    #(#arms)*
+                    // same as the default `Object::call()`
+                    _ => match ::hatter::Object::get(self, method) {
+                        Some(f @ ::hatter::Value::Fn(..)) => {
+                            let vals = ::std::mem::take(&mut *args);
+                            args.env.call(&f, vals)
+                        }
+                        _ => Err(hatter_error(format!(
+                            "{} has no method {}",
+                            ::hatter::Object::typename(self),
+                            method
+                        ))),
+                    },
+                }
+            }
+        }
+    })
+}
+
+/// Just the `#[hatter(...)]` attributes.
+fn hatter_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("hatter"))
+}
+
+/// "Cell" or "RefCell" if the type is one of them.
+fn cell_kind(ty: &Type) -> Option<String> {
+    if let Type::Path(path) = ty {
+        let last = path.path.segments.last()?.ident.to_string();
+        if last == "Cell" || last == "RefCell" {
+            return Some(last);
+        }
+    }
+    None
+}
+
+/// Does this function return a `Result`?
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
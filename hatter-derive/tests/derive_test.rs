@@ -0,0 +1,124 @@
+use {
+    hatter::{hatter_methods, Args, Env, HatterObject, Object, Result, Value},
+    std::cell::{Cell, RefCell},
+};
+
+#[derive(HatterObject)]
+#[hatter(name = "Person", methods)]
+struct User {
+    name: String,
+    age: usize,
+    tags: Vec<String>,
+    #[hatter(rename = "is-admin")]
+    admin: bool,
+    #[hatter(skip)]
+    #[allow(dead_code)]
+    password: String,
+    visits: Cell<usize>,
+    nickname: RefCell<String>,
+    wave: Value,
+}
+
+#[hatter_methods]
+impl User {
+    #[hatter(method)]
+    fn greet(&self, greeting: String) -> String {
+        format!("{}, {}!", greeting, self.name)
+    }
+
+    #[hatter(method)]
+    fn older(&self, years: usize) -> usize {
+        self.age + years
+    }
+
+    #[hatter(method)]
+    fn nth_tag(&self, idx: usize) -> Result<String> {
+        match self.tags.get(idx) {
+            Some(tag) => Ok(tag.clone()),
+            None => Err(hatter::Error::new(
+                hatter::ErrorKind::RuntimeError,
+                format!("no tag {}", idx),
+                0,
+                0,
+            )),
+        }
+    }
+
+    #[allow(dead_code)]
+    fn hidden(&self) -> bool {
+        true
+    }
+}
+
+#[derive(HatterObject)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+fn user() -> User {
+    User {
+        name: "Alice".into(),
+        age: 30,
+        tags: vec!["rust".into(), "hats".into()],
+        admin: true,
+        password: "hunter2".into(),
+        visits: Cell::new(1),
+        nickname: RefCell::new("Al".into()),
+        wave: Value::from(|_: Args| Ok(Value::from("👋"))),
+    }
+}
+
+fn render(src: &str) -> Result<String> {
+    let mut env = Env::new();
+    env.set("user", Value::Object(std::rc::Rc::new(user())));
+    env.set(
+        "point",
+        Value::Object(std::rc::Rc::new(Point { x: 1.5, y: 2.0 })),
+    );
+    env.render(src)
+}
+
+#[test]
+fn test_typename() {
+    assert_eq!("Person", user().typename());
+    assert_eq!("Point", Point { x: 0.0, y: 0.0 }.typename());
+}
+
+#[test]
+fn test_get() {
+    assert_eq!("Alice", render("user.name").unwrap());
+    assert_eq!("30", render("user.age").unwrap());
+    assert_eq!("hats", render("user.tags[1]").unwrap());
+    assert_eq!("true", render("user['is-admin']").unwrap());
+    assert_eq!("", render("user.password").unwrap());
+    assert_eq!("", render("user.admin").unwrap());
+    assert_eq!("1.5", render("point.x").unwrap());
+}
+
+#[test]
+fn test_set() {
+    assert_eq!("2", render("user.visits = 2\nuser.visits").unwrap());
+    assert_eq!(
+        "Ally",
+        render("user.nickname = 'Ally'\nuser.nickname").unwrap()
+    );
+    assert_eq!("1", render("user.visits = 'lots'\nuser.visits").unwrap());
+    assert_eq!("1", render("user.visits = -5\nuser.visits").unwrap());
+    assert_eq!("1", render("user.visits = 1.5\nuser.visits").unwrap());
+    assert_eq!("Alice", render("user.name = 'Bob'\nuser.name").unwrap());
+}
+
+#[test]
+fn test_methods() {
+    assert_eq!("Hiya, Alice!", render("user.greet('Hiya')").unwrap());
+    assert_eq!("35", render("user.older(5)").unwrap());
+    assert_eq!("rust", render("user.nth_tag(0)").unwrap());
+    assert!(render("user.nth_tag(5)").is_err());
+    assert!(render("user.greet()").is_err());
+    assert!(render("user.older('five')").is_err());
+    assert!(render("user.hidden()").is_err());
+    assert!(render("point.greet('Hi')").is_err());
+    // Fn fields are callable like methods
+    assert_eq!("👋", render("user.wave()").unwrap());
+}
@@ -1,7 +1,7 @@
 //! The lexer turns a string of source code into Tokens.
 
 use {
-    crate::{Result, Syntax, SyntaxTrait, Token},
+    crate::{Error, Result, Syntax, SyntaxTrait, Token},
     std::{iter::Peekable, mem, str::CharIndices},
 };
 
@@ -15,6 +15,7 @@ struct Lexer<'s> {
     mode: Mode,                       // lexing mode
     modes: Vec<Mode>,                 // stack of recent modes
     style: Style,                     // tabs or spaces?
+    errors: Vec<Error>,               // problems we scanned past
 }
 
 #[derive(Debug, PartialEq)]
@@ -33,9 +34,20 @@ enum Style {
 
 /// Scans source code and produces a list of `Token`s.
 pub fn scan<'s>(source: &'s str) -> Result<Vec<Token<'s>>> {
+    let (tokens, mut errors) = scan_all(source);
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+/// Scans all the source code, skipping past problems instead of
+/// stopping at the first one. Returns the `Token`s and every error.
+pub fn scan_all(source: &str) -> (Vec<Token<'_>>, Vec<Error>) {
     let mut lexer = Lexer::from(source);
-    lexer.scan()?;
-    Ok(lexer.tokens)
+    lexer.scan();
+    (lexer.tokens, lexer.errors)
 }
 
 impl<'s> Lexer<'s> {
@@ -51,6 +63,7 @@ impl<'s> Lexer<'s> {
             style: Style::None,
             mode: Mode::None,
             modes: vec![],
+            errors: vec![],
         }
     }
 
@@ -96,9 +109,8 @@ impl<'s> Lexer<'s> {
     }
 
     /// Add single Syntax to tokens list.
-    fn append(&mut self, kind: Syntax) -> Result<()> {
+    fn append(&mut self, kind: Syntax) {
         self.tokens.push(Token::new(kind, self.pos, 1, ""));
-        Ok(())
     }
 
     /// Consume and discard input while check(peek()) is true.
@@ -134,120 +146,16 @@ impl<'s> Lexer<'s> {
         matches!(self.mode, Mode::Container)
     }
 
-    /// Turn `source` into vector of `Token`, or error.
-    fn scan(&mut self) -> Result<()> {
+    /// Turn `source` into vector of `Token`, collecting errors as we go.
+    fn scan(&mut self) {
         while let Some(c) = self.next() {
             let start = self.pos;
-            let kind = match c {
-                '\n' => self.scan_newline()?,
-                ';' => Syntax::Semi,
-                ',' => Syntax::Comma,
-                '"' | '\'' | '`' => self.scan_string(c)?,
-                '!' => {
-                    if self.peek_is('!') {
-                        Syntax::Op
-                    } else {
-                        self.scan_op()?
-                    }
-                }
-
-                ':' => {
-                    if self.peek_is('=') || self.in_tag() {
-                        self.scan_op()?
-                    } else {
-                        Syntax::Colon
-                    }
-                }
-
-                '-' => {
-                    if self.peek().filter(|c| c.is_numeric()).is_some() {
-                        self.scan_number()?
-                    } else {
-                        self.scan_op()?
-                    }
-                }
-
-                '#' => {
-                    if self
-                        .peek()
-                        .filter(|c| **c == '{' || c.is_alphabetic())
-                        .is_some()
-                    {
-                        self.scan_op()?
-                    } else {
-                        self.scan_comment()?
-                    }
-                }
-
-                '[' => {
-                    self.set_mode(Mode::Container);
-                    Syntax::LStaple
-                }
-                ']' => {
-                    self.pop_mode();
-                    Syntax::RStaple
-                }
-
-                '(' => {
-                    if self.in_tag() {
-                        self.scan_open_paren_in_tag()?
-                    } else {
-                        self.set_mode(Mode::Container);
-                        Syntax::LParen
-                    }
-                }
-                ')' => {
-                    if !self.in_tag() {
-                        self.pop_mode();
-                    }
-                    Syntax::RParen
-                }
-
-                '{' => {
-                    if self.in_tag() {
-                        self.scan_word(true)?
-                    } else {
-                        self.set_mode(Mode::Container);
-                        Syntax::LCurly
-                    }
-                }
-                '}' => {
-                    self.pop_mode();
-                    Syntax::RCurly
-                }
-
-                '<' => self.scan_left_arrow()?,
-                '>' => {
-                    if self.in_tag() {
-                        self.mode = Mode::None;
-                        Syntax::RCaret
-                    } else {
-                        self.scan_op()?
-                    }
-                }
-                '=' => {
-                    if self.in_tag() {
-                        Syntax::Equal
-                    } else {
-                        self.scan_op()?
-                    }
-                }
-                '/' => {
-                    if self.in_tag() {
-                        Syntax::Slash
-                    } else {
-                        self.scan_op()?
-                    }
-                }
-
-                _ if c.is_whitespace() => {
-                    self.eat(|c| c.is_whitespace());
+            let kind = match self.scan_token(c) {
+                Ok(kind) => kind,
+                Err(e) => {
+                    self.errors.push(e);
                     continue;
                 }
-
-                _ if c.is_numeric() => self.scan_number()?,
-                _ if c.is_alphabetic() || c == '_' => self.scan_word(false)?,
-                _ => self.scan_op()?,
             };
 
             // skip empty tokens
@@ -266,21 +174,134 @@ impl<'s> Lexer<'s> {
 
         // Add final semicolon before EOF, if not present.
         if !self.prev_is(Syntax::Semi) && !self.prev_is(Syntax::Dedent) {
-            self.append(Syntax::Semi)?;
+            self.append(Syntax::Semi);
         }
 
         // Close open indents
         while !self.indents.is_empty() {
             self.indents.pop();
-            self.append(Syntax::Dedent)?;
+            self.append(Syntax::Dedent);
         }
 
         // Trim leading ;
         while !self.tokens.is_empty() && self.tokens[0].kind == Syntax::Semi {
             self.tokens.remove(0);
         }
+    }
 
-        Ok(())
+    /// Scan the token starting with `c`.
+    fn scan_token(&mut self, c: char) -> Result<Syntax> {
+        Ok(match c {
+            '\n' => self.scan_newline()?,
+            ';' => Syntax::Semi,
+            ',' => Syntax::Comma,
+            '"' | '\'' | '`' => self.scan_string(c)?,
+            '!' => {
+                if self.peek_is('!') {
+                    Syntax::Op
+                } else {
+                    self.scan_op()?
+                }
+            }
+
+            ':' => {
+                if self.peek_is('=') || self.in_tag() {
+                    self.scan_op()?
+                } else {
+                    Syntax::Colon
+                }
+            }
+
+            '-' => {
+                if self.peek().filter(|c| c.is_numeric()).is_some() {
+                    self.scan_number()?
+                } else {
+                    self.scan_op()?
+                }
+            }
+
+            '#' => {
+                if self
+                    .peek()
+                    .filter(|c| **c == '{' || c.is_alphabetic())
+                    .is_some()
+                {
+                    self.scan_op()?
+                } else {
+                    self.scan_comment()?
+                }
+            }
+
+            '[' => {
+                self.set_mode(Mode::Container);
+                Syntax::LStaple
+            }
+            ']' => {
+                self.pop_mode();
+                Syntax::RStaple
+            }
+
+            '(' => {
+                if self.in_tag() {
+                    self.scan_open_paren_in_tag()?
+                } else {
+                    self.set_mode(Mode::Container);
+                    Syntax::LParen
+                }
+            }
+            ')' => {
+                if !self.in_tag() {
+                    self.pop_mode();
+                }
+                Syntax::RParen
+            }
+
+            '{' => {
+                if self.in_tag() {
+                    self.scan_word(true)?
+                } else {
+                    self.set_mode(Mode::Container);
+                    Syntax::LCurly
+                }
+            }
+            '}' => {
+                self.pop_mode();
+                Syntax::RCurly
+            }
+
+            '<' => self.scan_left_arrow()?,
+            '>' => {
+                if self.in_tag() {
+                    self.mode = Mode::None;
+                    Syntax::RCaret
+                } else {
+                    self.scan_op()?
+                }
+            }
+            '=' => {
+                if self.in_tag() {
+                    Syntax::Equal
+                } else {
+                    self.scan_op()?
+                }
+            }
+            '/' => {
+                if self.in_tag() {
+                    Syntax::Slash
+                } else {
+                    self.scan_op()?
+                }
+            }
+
+            _ if c.is_whitespace() => {
+                self.eat(|c| c.is_whitespace());
+                Syntax::None
+            }
+
+            _ if c.is_numeric() => self.scan_number()?,
+            _ if c.is_alphabetic() || c == '_' => self.scan_word(false)?,
+            _ => self.scan_op()?,
+        })
     }
 
     /// Scan a single line comment.
@@ -343,9 +364,12 @@ impl<'s> Lexer<'s> {
 
     /// Scan until closing delimiter.
     /// Call when the first char of the string will be `next()`.
+    /// "Double" and 'single' quoted strings have escape codes, while
+    /// `backtick` and triple quoted strings are raw.
     fn scan_string(&mut self, delimiter: char) -> Result<Syntax> {
+        let (chars, pos, cur) = (self.chars.clone(), self.pos, self.cur);
         let mut start = self.pos + 1;
-        let mut prev = '0'; // TODO: actual escape code parsing
+        let mut prev = '0';
         let mut triple = false;
 
         // """ or ''' or ```
@@ -363,17 +387,27 @@ impl<'s> Lexer<'s> {
             }
         }
 
+        let escapes = !triple && delimiter != '`';
         while let Some(c) = self.next() {
+            if c == '\\' && escapes {
+                // bad escapes are reported, but the string keeps going
+                if let Err(e) = self.scan_escape() {
+                    self.errors.push(e);
+                }
+                continue;
+            }
             if c == delimiter && prev != '\\' {
                 if !triple {
                     let len = self.pos - start - 1;
                     let lit = &self.source[start..=start + len];
-                    self.tokens.push(Token::new(
+                    let tok = Token::new(
                         Syntax::String(delimiter == '"' && lit.contains('{')),
                         start,
                         len,
                         lit,
-                    ));
+                    );
+                    self.tokens
+                        .push(if escapes { tok.with_escapes() } else { tok });
                     return Ok(Syntax::None);
                 } else if self.peek_is(delimiter) {
                     self.next();
@@ -399,6 +433,20 @@ impl<'s> Lexer<'s> {
             prev = c;
         }
 
+        // go back and end the string at the end of its line, so we can
+        // keep scanning
+        self.chars = chars;
+        self.pos = pos;
+        self.cur = cur;
+        self.eat(|c| c != '\n');
+        let end = self.pos + self.cur.len_utf8();
+        self.tokens.push(Token::new(
+            Syntax::String(false),
+            start,
+            end.saturating_sub(start),
+            &self.source[start.min(end)..end],
+        ));
+
         scan_error!(
             start,
             1,
@@ -407,6 +455,39 @@ impl<'s> Lexer<'s> {
         )
     }
 
+    /// Check the escape code after a `\` in a string:
+    /// \n \t \r \0 \\ \" \' \` \{ \} or \u{1F600}
+    fn scan_escape(&mut self) -> Result<()> {
+        let start = self.pos;
+        match self.next() {
+            Some('n' | 't' | 'r' | '0' | '\\' | '"' | '\'' | '`' | '{' | '}') => Ok(()),
+            Some('u') => {
+                if !self.peek_is('{') {
+                    return scan_error!(start, 2, "Expected { after \\u");
+                }
+                self.next();
+                let digits = self.pos + 1;
+                self.eat(|c| c.is_ascii_hexdigit());
+                let hex = &self.source[digits..self.pos + 1];
+                if !self.peek_is('}') {
+                    return scan_error!(start, self.pos + 1 - start, "Unclosed \\u{...} escape");
+                }
+                self.next();
+                match u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                    Some(_) if hex.len() <= 6 => Ok(()),
+                    _ => scan_error!(
+                        start,
+                        self.pos + 1 - start,
+                        "Invalid unicode escape: \\u{{{}}}",
+                        hex
+                    ),
+                }
+            }
+            Some(c) => scan_error!(start, 1 + c.len_utf8(), "Unknown escape code: \\{}", c),
+            None => Ok(()),
+        }
+    }
+
     /// Scan a word, which may have {interpolation.with(some, whitespace)}.
     fn scan_op(&mut self) -> Result<Syntax> {
         self.eat(|c| c.is_op());
@@ -425,7 +506,7 @@ impl<'s> Lexer<'s> {
                     if curlies == 0 {
                         in_code = false;
                     } else {
-                        curlies -= 0;
+                        curlies -= 1;
                     }
                 } else if c == '{' {
                     curlies += 1;
@@ -453,6 +534,11 @@ impl<'s> Lexer<'s> {
             "while" => Syntax::While,
             "in" => Syntax::In,
             "fn" => Syntax::Fn,
+            "block" => Syntax::Block,
+            "extends" => Syntax::Extends,
+            // `try(f, args)` is a function call
+            "try" if !self.peek_is('(') => Syntax::Try,
+            "rescue" => Syntax::Rescue,
             "true" => Syntax::Bool(true),
             "false" => Syntax::Bool(false),
             _ => Syntax::Word,
@@ -532,8 +618,10 @@ impl<'s> Lexer<'s> {
             // ignore stacked newlines
             self.eat(|c| c == '\n');
 
-            // tabs vs spaces error checking
-            self.check_indent_style()?;
+            // tabs vs spaces error checking. keep going to find more.
+            if let Err(e) = self.check_indent_style() {
+                self.errors.push(e);
+            }
 
             // count indent
             while self.peek_is(' ') || self.peek_is('\t') {
@@ -579,11 +667,11 @@ impl<'s> Lexer<'s> {
 
         // lesser indent than current depth: Dedent
         if indent < last {
-            self.append(Syntax::Semi)?;
+            self.append(Syntax::Semi);
             while !self.indents.is_empty() {
                 if indent < self.indents[self.indents.len() - 1] {
                     self.indents.pop();
-                    self.append(Syntax::Dedent)?;
+                    self.append(Syntax::Dedent);
                 } else {
                     break;
                 }
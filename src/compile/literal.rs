@@ -0,0 +1,65 @@
+use {
+    crate::{Symbol, Value},
+    std::fmt,
+};
+
+/// A literal value in the AST: `true`, `3.14`, `"hi"`, etc.
+///
+/// Unlike `Value`, which is built on `Rc<RefCell<..>>`, Literals are
+/// `Send + Sync` so compiled templates can be shared across threads.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    None,
+    Bool(bool),
+    Number(f64),
+    String(Symbol),
+}
+
+impl Literal {
+    /// Convert into a runtime Value.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Literal::None => Value::None,
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Number(n) => Value::Number(*n),
+            Literal::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(lit: &Literal) -> Value {
+        lit.to_value()
+    }
+}
+
+impl From<Literal> for Value {
+    fn from(lit: Literal) -> Value {
+        lit.to_value()
+    }
+}
+
+macro_rules! into_literal {
+    ($type:ty, $var:ident, $item:ident => $conv:expr) => {
+        impl From<$type> for Literal {
+            fn from($item: $type) -> Self {
+                Literal::$var($conv)
+            }
+        }
+    };
+}
+
+into_literal!(bool, Bool, b => b);
+into_literal!(f64, Number, n => n);
+into_literal!(i32, Number, n => n.into());
+into_literal!(usize, Number, n => n as f64);
+into_literal!(Symbol, String, s => s);
+into_literal!(&Symbol, String, s => s.clone());
+into_literal!(&str, String, s => s.into());
+into_literal!(String, String, s => s.into());
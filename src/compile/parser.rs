@@ -1,7 +1,7 @@
 //! The parser turns a slice of `Token` into a Vec of `Stmt`.
 
 use {
-    crate::{scan, Error, Result, Stmt, Symbol, Syntax, Tag, Token},
+    crate::{scan, Error, Result, Span, Stmt, Symbol, Syntax, Tag, Token},
     std::mem,
 };
 
@@ -14,24 +14,34 @@ pub struct Parser<'s, 't> {
     ast: Vec<Stmt>,          // what we're building
     pos: usize,              // position in tokens vec
     tags: usize,             // open tags
+    errors: Vec<Error>,      // problems we skipped past
 
     #[cfg(debug_assertions)]
     peeked: usize, // infinite loop protection hack
 }
 
 /// Main method. Turn a slice of `Token` into a Vec of `Stmt`.
-pub fn parse<'t>(tokens: &'t [Token]) -> Result<Vec<Stmt>> {
+pub fn parse(tokens: &[Token]) -> Result<Vec<Stmt>> {
     let mut parser = Parser::from(tokens);
     parser.parse()?;
     Ok(parser.ast)
 }
 
+/// Parse all the `Token`s, skipping broken statements instead of
+/// stopping at the first one. Returns what parsed and every error.
+pub fn parse_all(tokens: &[Token]) -> (Vec<Stmt>, Vec<Error>) {
+    let mut parser = Parser::from(tokens);
+    parser.parse_all();
+    (parser.ast, parser.errors)
+}
+
 impl<'s, 't> Parser<'s, 't> {
     /// Create a `Parser` from a slice of `Token`.
     pub fn from(tokens: &'t [Token<'s>]) -> Parser<'s, 't> {
         Parser {
             tokens,
             ast: vec![],
+            errors: vec![],
             tags: 0,
             pos: 0,
 
@@ -42,33 +52,86 @@ impl<'s, 't> Parser<'s, 't> {
 
     /// Parse `TokenStream` into `AST`.
     pub fn parse(&mut self) -> Result<()> {
+        self.parse_all();
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors.remove(0))
+        }
+    }
+
+    /// Parse `TokenStream` into `AST`, collecting errors as we go.
+    fn parse_all(&mut self) {
         while !self.peek_eof() {
-            let stmt = self.stmt()?;
-            self.ast.push(stmt);
+            if let Some(stmt) = self.stmt_or_skip() {
+                self.ast.push(stmt);
+            }
             self.eat(Syntax::Semi);
         }
-        Ok(())
+    }
+
+    /// Parse a statement. If it's broken, remember the error and skip
+    /// to the next line at the same indentation so we can keep going.
+    fn stmt_or_skip(&mut self) -> Option<Stmt> {
+        let (start, tags) = (self.pos, self.tags);
+        match self.stmt() {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.tags = tags;
+                self.resync();
+                if self.pos == start {
+                    self.skip();
+                }
+                None
+            }
+        }
+    }
+
+    /// Skip past the end of the current line, and any lines indented
+    /// under it. Stops at the end of the current block.
+    fn resync(&mut self) {
+        let mut depth = 0;
+        while let Some(tok) = self.peek() {
+            match tok.kind {
+                Syntax::Indent => depth += 1,
+                Syntax::Dedent if depth == 0 => return,
+                Syntax::Dedent => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.skip();
+                        return;
+                    }
+                }
+                Syntax::Semi if depth == 0 => {
+                    self.skip();
+                    return;
+                }
+                _ => {}
+            }
+            self.skip();
+        }
     }
 
     /// Peek at next `Token`.
-    fn peek(&mut self) -> Option<Token> {
+    fn peek(&mut self) -> Option<Token<'_>> {
         #[cfg(debug_assertions)]
         {
             self.peeked += 1;
             if self.peeked > STACK_SIZE {
-                panic!("infinite loop while peek()ing: {:?}", self.tokens.get(0));
+                panic!("infinite loop while peek()ing: {:?}", self.tokens.first());
             }
         }
         self.tokens.get(self.pos).copied()
     }
 
     /// Peek two ahead.
-    fn peek2(&mut self) -> Option<Token> {
+    fn peek2(&mut self) -> Option<Token<'_>> {
         #[cfg(debug_assertions)]
         {
             self.peeked += 1;
             if self.peeked > STACK_SIZE {
-                panic!("infinite loop while peek()ing: {:?}", self.tokens.get(0));
+                panic!("infinite loop while peek()ing: {:?}", self.tokens.first());
             }
         }
         self.tokens.get(self.pos + 1).copied()
@@ -94,15 +157,6 @@ impl<'s, 't> Parser<'s, 't> {
         self.peek().is_none()
     }
 
-    /// Advance iterator an return next `Token`.
-    fn try_next(&mut self) -> Option<Token> {
-        if self.pos < self.tokens.len() {
-            Some(self.next())
-        } else {
-            None
-        }
-    }
-
     /// Move iterator back.
     fn back(&mut self) {
         if self.pos > 0 {
@@ -111,7 +165,7 @@ impl<'s, 't> Parser<'s, 't> {
     }
 
     /// Advance iterator an return next `Token`.
-    fn next(&mut self) -> Token {
+    fn next(&mut self) -> Token<'_> {
         #[cfg(debug_assertions)]
         {
             self.peeked = 0;
@@ -121,6 +175,14 @@ impl<'s, 't> Parser<'s, 't> {
         *self.tokens.get(pos).unwrap()
     }
 
+    /// Span of the token we just consumed.
+    fn prev_span(&self) -> Span {
+        self.tokens
+            .get(self.pos.saturating_sub(1))
+            .map(|t| t.span())
+            .unwrap_or_default()
+    }
+
     /// Skip one token.
     fn skip(&mut self) {
         let _ = self.next();
@@ -137,7 +199,7 @@ impl<'s, 't> Parser<'s, 't> {
     fn error<T, S: AsRef<str>>(&mut self, msg: S) -> Result<T> {
         use crate::ErrorKind::ParseError;
 
-        Err(if let Some(got) = self.try_next() {
+        Err(if let Some(got) = self.peek() {
             Error::new(
                 ParseError,
                 format!("expected {}, got {:?}", msg.as_ref(), got.kind),
@@ -148,7 +210,7 @@ impl<'s, 't> Parser<'s, 't> {
             Error::new(
                 ParseError,
                 format!("expected {}, got EOF", msg.as_ref()),
-                0,
+                self.tokens.last().map(|t| t.pos + t.len).unwrap_or(0),
                 0,
             )
         })
@@ -156,7 +218,7 @@ impl<'s, 't> Parser<'s, 't> {
 
     /// Consumes and returns the next token if it's of `kind`,
     /// otherwise errors.
-    fn expect(&mut self, kind: Syntax) -> Result<Token> {
+    fn expect(&mut self, kind: Syntax) -> Result<Token<'_>> {
         if self.peek_kind() == kind {
             Ok(self.next())
         } else {
@@ -186,60 +248,65 @@ impl<'s, 't> Parser<'s, 't> {
             _ => return self.error("String"),
         };
 
-        let lit = tok.to_string();
-        if is_interpolated && lit.contains('{') {
-            let mut parts = vec![];
-            let mut idx = 0;
-            while let Some(i) = lit[idx..].find('{') {
-                // check for escaped \{}
-                if i > 0 && lit[idx..].bytes().nth(i - 1).unwrap_or(b'0') == b'\\' {
-                    parts.push(Stmt::Value(lit[idx..i + idx - 1].into()));
-                    parts.push(Stmt::Value(lit[idx + i..i + idx + 1].into()));
-                    idx += i + 1;
-                    continue;
-                }
+        let (lit, pos, span, escapes) = (tok.to_string(), tok.pos, tok.span(), tok.has_escapes());
+        if !is_interpolated || !lit.contains('{') {
+            return Ok(Stmt::Value(
+                if escapes { unescape_str(&lit) } else { lit }.into(),
+            ));
+        }
 
-                {
-                    let s = &lit[idx..i + idx];
-                    if !s.is_empty() {
-                        parts.push(Stmt::Value(s.into()));
-                    }
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut idx = 0;
+        while let Some(c) = lit[idx..].chars().next() {
+            idx += c.len_utf8();
+            match c {
+                '\\' if escapes => {
+                    let (c, len) = unescape(&lit[idx..]);
+                    text.push(c);
+                    idx += len;
                 }
-                idx += i + 1;
-                let mut end = idx;
-                for (x, b) in lit[idx..].bytes().enumerate() {
-                    if b == b'}' {
-                        end = idx + x;
-                        break;
+                // words and raw strings can still escape {
+                '\\' if lit[idx..].starts_with('{') => {
+                    text.push('{');
+                    idx += 1;
+                }
+                '{' => {
+                    if !text.is_empty() {
+                        parts.push(Stmt::Value(mem::take(&mut text).into()));
                     }
+                    let end = closing_curly(&lit[idx..]).map_or(lit.len(), |i| idx + i);
+                    // point the {code}'s tokens at their place in the source
+                    let offset = pos + idx;
+                    let mut exprs = scan(&lit[idx..end])
+                        .map_err(|mut e| {
+                            e.pos += offset;
+                            e
+                        })
+                        .and_then(|mut tokens| {
+                            tokens.iter_mut().for_each(|t| t.pos += offset);
+                            parse(&tokens)
+                        })?;
+                    parts.append(&mut exprs);
+                    idx = (end + 1).min(lit.len());
                 }
-                // What! Rust 'lifetime magic.
-                let mut exprs = scan(&lit[idx..end])
-                    .and_then(|t| parse(&t))
-                    .map_err(|mut e| {
-                        e.pos += tok.pos + idx - 1; // probably not right yet...
-                        e
-                    })?;
-                parts.append(&mut exprs);
-                idx = end + 1;
-            }
-            if idx < lit.len() {
-                parts.push(Stmt::Value(lit[idx..].into()));
-            }
-            if parts.len() == 1 {
-                Ok(parts.remove(0))
-            } else {
-                Ok(Stmt::Call(bx!(Stmt::Word("concat".into())), parts))
+                c => text.push(c),
             }
+        }
+        if !text.is_empty() {
+            parts.push(Stmt::Value(text.into()));
+        }
+        if parts.len() == 1 {
+            Ok(parts.remove(0))
         } else {
-            Ok(Stmt::Value(lit.into()))
+            Ok(Stmt::call("concat", parts, span))
         }
     }
 
     /// Parse a word.
     fn word(&mut self) -> Result<Stmt> {
         let word = self.expect(Syntax::Word)?;
-        Ok(Stmt::Word(word.to_str().into()))
+        Ok(Stmt::Word(word.to_sym(), word.span()))
     }
 
     /// Parse a function literal.
@@ -278,10 +345,9 @@ impl<'s, 't> Parser<'s, 't> {
     fn op_expr(&mut self, min_power: u8) -> Result<Stmt> {
         // check for unary
         if self.peek_is(Syntax::Op) {
-            return Ok(Stmt::Call(
-                bx!(Stmt::Word(self.next().to_sym())),
-                vec![self.op_expr(min_power)?],
-            ));
+            let op = self.next();
+            let (op, span) = (op.to_sym(), op.span());
+            return Ok(Stmt::call(op, vec![self.op_expr(min_power)?], span));
         }
 
         let mut left = self.atom()?;
@@ -295,16 +361,18 @@ impl<'s, 't> Parser<'s, 't> {
                 if power < min_power {
                     break;
                 }
-                let op = self.next().to_string();
+                let op = self.next();
+                let (op, start) = (op.to_string(), left.span().unwrap_or(op.span()));
                 match op.as_ref() {
                     "(" => {
-                        left = Stmt::Call(bx!(left), self.args()?);
+                        let args = self.args()?;
+                        left = Stmt::Call(bx!(left), args, start.to(self.prev_span()));
                         continue;
                     }
                     "[" => {
-                        left =
-                            Stmt::Call(bx!(Stmt::Word("index".into())), vec![left, self.expr()?]);
+                        let args = vec![left, self.expr()?];
                         self.expect(Syntax::RStaple)?;
+                        left = Stmt::call("index", args, start.to(self.prev_span()));
                         continue;
                     }
                     _ => unimplemented!("op: {}", op),
@@ -315,18 +383,24 @@ impl<'s, 't> Parser<'s, 't> {
             if op_power <= min_power {
                 break;
             }
-            let op = self.next().to_sym();
+            let op = self.next();
+            let (op, span) = (op.to_sym(), op.span());
             match op.as_ref() {
+                // `x | f(y)` => `f(x, y)`
+                "|" => {
+                    left = self.pipe(left, span)?;
+                    continue;
+                }
                 ":=" | "=" => {
                     let reassign = op == "=";
                     match left {
-                        Stmt::Word(name) => {
-                            return Ok(Stmt::Assign(name, bx!(self.expr()?), reassign));
+                        Stmt::Word(name, span) => {
+                            return Ok(Stmt::Assign(name, bx!(self.expr()?), reassign, span));
                         }
-                        Stmt::Call(ex, mut args) => match ex.to_str() {
+                        Stmt::Call(ex, mut args, span) => match ex.to_str() {
                             "index" | "." => {
                                 args.push(self.expr()?);
-                                return Ok(Stmt::Call(bx!(Stmt::Word("set_index".into())), args));
+                                return Ok(Stmt::call("set_index", args, span));
                             }
                             _ => {
                                 return self.error("Word or index");
@@ -337,9 +411,9 @@ impl<'s, 't> Parser<'s, 't> {
                 }
                 // convert word to str, ex: map.key => .(map, "key")
                 "." if self.peek_is(Syntax::Word) => match self.op_expr(op_power)? {
-                    Stmt::Word(word) => {
-                        left =
-                            Stmt::Call(bx!(Stmt::Word(op)), vec![left, Stmt::Value(word.into())]);
+                    Stmt::Word(word, end) => {
+                        let span = left.span().unwrap_or(span).to(end);
+                        left = Stmt::call(op, vec![left, Stmt::Value(word.into())], span);
                         continue;
                     }
                     _ => return self.error("Word"),
@@ -348,23 +422,44 @@ impl<'s, 't> Parser<'s, 't> {
                 _ if !matches!(op.as_ref(), "==" | "!=" | ">=" | "<=" | "..=")
                     && matches!(op.bytes().last(), Some(b'=')) =>
                 {
+                    let var = left.span().unwrap_or(span);
                     return Ok(Stmt::Assign(
                         left.to_str().into(),
-                        bx!(Stmt::Call(
-                            bx!(Stmt::Word(op.to_str().trim_end_matches('=').into())),
-                            vec![left, self.expr()?]
+                        bx!(Stmt::call(
+                            op.to_str().trim_end_matches('='),
+                            vec![left, self.expr()?],
+                            span
                         )),
                         true, // reassignment
+                        var,
                     ));
                 }
                 _ => {}
             }
             let right = self.op_expr(op_power)?;
-            left = Stmt::Call(bx!(Stmt::Word(op)), vec![left, right]);
+            left = Stmt::call(op, vec![left, right], span);
         }
         Ok(left)
     }
 
+    /// Parse the right side of a pipe, `value | f` or `value | f(args)`,
+    /// into a call with `value` as the first arg.
+    fn pipe(&mut self, value: Stmt, span: Span) -> Result<Stmt> {
+        let start = value.span().unwrap_or(span);
+        // just the function: `str.upcase` but not `upcase(...)`
+        let f = self.op_expr(16)?;
+        let mut args = vec![value];
+        if self.peek_is(Syntax::LParen) {
+            self.skip();
+            let mut rest = self.args()?;
+            if matches!(rest.first(), Some(Stmt::Args(..))) {
+                return pos_error!(span.pos, "Can't pipe into a call with keyword args");
+            }
+            args.append(&mut rest);
+        }
+        Ok(Stmt::Call(bx!(f), args, start.to(self.prev_span())))
+    }
+
     /// Parse an indivisible unit, as the Ancient Greeks would say.
     fn atom(&mut self) -> Result<Stmt> {
         match self.peek_kind() {
@@ -418,6 +513,9 @@ impl<'s, 't> Parser<'s, 't> {
                         break;
                     }
                     let key = match self.peek_kind() {
+                        Syntax::String(..) if self.peek().is_some_and(|t| t.has_escapes()) => {
+                            unescape_str(self.next().literal()).into()
+                        }
                         Syntax::Word | Syntax::String(..) | Syntax::Number | Syntax::Bool(..) => {
                             self.next().to_sym()
                         }
@@ -520,7 +618,7 @@ impl<'s, 't> Parser<'s, 't> {
                 Syntax::Dedent => break,
 
                 // everything else is a stmt
-                _ => block.push(self.stmt()?),
+                _ => block.extend(self.stmt_or_skip()),
             };
         }
         self.expect(Syntax::Dedent)?;
@@ -567,6 +665,7 @@ impl<'s, 't> Parser<'s, 't> {
                 }
 
                 // everything else is a stmt
+                _ if indented => block.extend(self.stmt_or_skip()),
                 _ => block.push(self.stmt()?),
             };
         }
@@ -594,6 +693,12 @@ impl<'s, 't> Parser<'s, 't> {
             Syntax::Def => self.def_stmt(),
             Syntax::If => self.if_stmt(),
             Syntax::For => self.for_stmt(),
+            Syntax::Block => self.block_stmt(),
+            Syntax::Try => self.try_stmt(),
+            Syntax::Extends => {
+                self.skip();
+                Ok(Stmt::Extends(bx!(self.expr()?)))
+            }
             Syntax::While => {
                 self.skip();
                 Ok(Stmt::While(bx!(self.expr()?), self.block()?))
@@ -641,14 +746,52 @@ impl<'s, 't> Parser<'s, 't> {
         Ok(Stmt::For(key, val, bx!(iter), body))
     }
 
+    /// Parse a named block, with optional default content:
+    ///     block body
+    ///     block title do "Default Title"
+    fn block_stmt(&mut self) -> Result<Stmt> {
+        self.expect(Syntax::Block)?;
+        let name = self.expect(Syntax::Word)?.to_sym();
+        let body = if matches!(self.peek_kind(), Syntax::Indent | Syntax::Do) {
+            self.block()?
+        } else {
+            vec![]
+        };
+        Ok(Stmt::Block(name, body))
+    }
+
+    /// Parse a `try` block, with an optional `rescue` block that runs
+    /// if it fails:
+    ///     try
+    ///         widget()
+    ///     rescue err
+    ///         <p.error> err
+    fn try_stmt(&mut self) -> Result<Stmt> {
+        self.expect(Syntax::Try)?;
+        let body = self.block()?;
+        let mut var = None;
+        let mut rescue = vec![];
+        if self.peek_is(Syntax::Semi) && self.peek2_is(Syntax::Rescue) {
+            self.skip();
+        }
+        if self.peek_is(Syntax::Rescue) {
+            self.skip();
+            if self.peek_is(Syntax::Word) {
+                var = Some(self.next().to_sym());
+            }
+            rescue = self.block()?;
+        }
+        Ok(Stmt::Try(body, var, rescue))
+    }
+
     /// Parse a function definition.
     fn def_stmt(&mut self) -> Result<Stmt> {
         self.expect(Syntax::Def)?;
         let name = match self.peek_kind() {
             Syntax::Word | Syntax::Op => self.next(),
             _ => return self.error("function name"),
-        }
-        .to_sym();
+        };
+        let (name, span) = (name.to_sym(), name.span());
 
         let mut args = vec![];
         if self.peek_is(Syntax::LParen) {
@@ -667,7 +810,7 @@ impl<'s, 't> Parser<'s, 't> {
         }
 
         let body = self.block()?;
-        Ok(Stmt::Assign(name, bx!(Stmt::Fn(args, body)), false))
+        Ok(Stmt::Assign(name, bx!(Stmt::Fn(args, body)), false, span))
     }
 
     /// Parse an if statement.
@@ -777,7 +920,7 @@ impl<'s, 't> Parser<'s, 't> {
         let mut head = true;
         loop {
             let next = self.next();
-            let pos = next.pos;
+            let (pos, span) = (next.pos, next.span());
             match next.kind {
                 Syntax::Semi => {}
                 Syntax::RCaret => break,
@@ -785,13 +928,19 @@ impl<'s, 't> Parser<'s, 't> {
                     tag.close();
                     self.tags -= 1;
                 }
+                // <div ...attrs> spreads a Map into attributes
+                Syntax::Op if next.literal() == "..." => {
+                    head = false;
+                    let attrs = self.attr()?;
+                    tag.add_spread(attrs);
+                }
                 Syntax::Op if head => match next.literal() {
                     "#" => {
                         let id = self.string()?;
                         if self.peek_is(Syntax::Equal) {
                             self.skip();
                             let cond = self.attr()?;
-                            tag.set_id(Stmt::Call(bx!(Stmt::Word("when".into())), vec![cond, id]));
+                            tag.set_id(Stmt::call("when", vec![cond, id], span));
                         } else {
                             tag.set_id(id);
                         }
@@ -801,10 +950,7 @@ impl<'s, 't> Parser<'s, 't> {
                         if self.peek_is(Syntax::Equal) {
                             self.skip();
                             let cond = self.attr()?;
-                            tag.add_class(Stmt::Call(
-                                bx!(Stmt::Word("when".into())),
-                                vec![cond, class],
-                            ));
+                            tag.add_class(Stmt::call("when", vec![cond, class], span));
                         } else {
                             tag.add_class(class);
                         }
@@ -819,10 +965,7 @@ impl<'s, 't> Parser<'s, 't> {
                         if self.peek_is(Syntax::Equal) {
                             self.skip();
                             let cond = self.attr()?;
-                            tag.add_attr(
-                                attr_name,
-                                Stmt::Call(bx!(Stmt::Word("when".into())), vec![cond, expr]),
-                            );
+                            tag.add_attr(attr_name, Stmt::call("when", vec![cond, expr], span));
                         } else {
                             tag.add_attr(attr_name, expr);
                         }
@@ -831,8 +974,18 @@ impl<'s, 't> Parser<'s, 't> {
                 },
                 Syntax::Word | Syntax::String(true) => {
                     head = false;
+                    let braced = next.literal().starts_with('{') && next.literal().ends_with('}');
                     self.back();
                     let name = self.string()?;
+                    // <input {attrs}> is the same as <input ...attrs>
+                    if braced
+                        && !self.peek_is(Syntax::Equal)
+                        && !matches!(&name, Stmt::Value(..))
+                        && !matches!(&name, Stmt::Call(f, ..) if f.to_str() == "concat")
+                    {
+                        tag.add_spread(name);
+                        continue;
+                    }
                     // single word attributes, like `defer`
                     if !self.peek_is(Syntax::Equal) {
                         tag.add_attr(name, Stmt::Value(true.into()));
@@ -840,9 +993,10 @@ impl<'s, 't> Parser<'s, 't> {
                     }
                     self.expect(Syntax::Equal)?;
                     match self.peek_kind() {
-                        Syntax::Bool(..) | Syntax::Number | Syntax::String(..) => {
-                            tag.add_attr(name, self.atom()?)
-                        }
+                        Syntax::Bool(..)
+                        | Syntax::Number
+                        | Syntax::String(..)
+                        | Syntax::LStaple => tag.add_attr(name, self.atom()?),
                         Syntax::Word => tag.add_attr(
                             name,
                             if self.peek().filter(|p| p.to_str().contains('{')).is_some() {
@@ -900,10 +1054,10 @@ impl<'s, 't> Parser<'s, 't> {
         if let Some(p) = self.peek() {
             match p.to_str() {
                 ":=" | "=" => 1,
-                "&&" => 2,
+                "&&" | "|" => 2,
                 "||" => 3,
                 "==" | "!=" | "<" | "<=" | ">" | ">=" | "<=>" => 4,
-                "+" | "-" | "|" | "^" => 5,
+                "+" | "-" | "^" => 5,
                 "*" | "/" | "%" | "<<" | ">>" | "&" => 6,
                 ".." | "..=" => 10,
                 "." => 20,
@@ -926,3 +1080,53 @@ impl<'s, 't> Parser<'s, 't> {
         Some(res)
     }
 }
+
+/// Turn the escape code after a `\` into the char it stands for, and
+/// how many bytes of `code` it used. The lexer has already checked it.
+fn unescape(code: &str) -> (char, usize) {
+    match code.chars().next() {
+        Some('n') => ('\n', 1),
+        Some('t') => ('\t', 1),
+        Some('r') => ('\r', 1),
+        Some('0') => ('\0', 1),
+        Some('u') if code.starts_with("u{") => {
+            let end = code.find('}').unwrap_or(code.len() - 1);
+            let c = u32::from_str_radix(&code[2..end], 16)
+                .ok()
+                .and_then(char::from_u32)
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            (c, end + 1)
+        }
+        Some(c) => (c, c.len_utf8()),
+        None => ('\\', 0),
+    }
+}
+
+/// Process all the escape codes in a string.
+fn unescape_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut idx = 0;
+    while let Some(i) = s[idx..].find('\\') {
+        out.push_str(&s[idx..idx + i]);
+        let (c, len) = unescape(&s[idx + i + 1..]);
+        out.push(c);
+        idx += i + 1 + len;
+    }
+    out.push_str(&s[idx..]);
+    out
+}
+
+/// Position of the `}` that closes an `{interpolation}`, skipping over
+/// nested `{}` like map literals.
+fn closing_curly(code: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in code.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
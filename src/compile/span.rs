@@ -0,0 +1,24 @@
+/// Where a `Stmt` came from in the source code, so runtime errors
+/// can point at it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub pos: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(pos: usize, len: usize) -> Span {
+        Span { pos, len }
+    }
+
+    /// A Span covering both this one and `other`.
+    pub fn to(self, other: Span) -> Span {
+        let end = (other.pos + other.len).max(self.pos + self.len);
+        Span::new(self.pos, end - self.pos)
+    }
+
+    /// Move this Span `offset` bytes further into the source.
+    pub fn shift(self, offset: usize) -> Span {
+        Span::new(self.pos + offset, self.len)
+    }
+}
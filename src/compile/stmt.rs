@@ -1,22 +1,25 @@
-use crate::{Symbol, Tag, Value};
+use crate::{Literal, Span, Symbol, Tag};
 
 /// Stmt is an AST node.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Stmt {
     None,
-    Value(Value),
-    Word(Symbol),
+    Value(Literal),
+    Word(Symbol, Span),
     List(Vec<Stmt>),
     Map(Vec<(Symbol, Stmt)>),
-    Call(Box<Stmt>, Vec<Stmt>), // fn, args
+    Call(Box<Stmt>, Vec<Stmt>, Span), // fn, args
     Return(Box<Stmt>),
     If(Vec<(Stmt, Vec<Stmt>)>),
     For(Option<Symbol>, Symbol, Box<Stmt>, Vec<Stmt>), // key, val, iter, body
     While(Box<Stmt>, Vec<Stmt>),
-    Assign(Symbol, Box<Stmt>, bool), // var, val, reassign?
+    Assign(Symbol, Box<Stmt>, bool, Span), // var, val, reassign?
     Tag(Tag),
-    Fn(Vec<Symbol>, Vec<Stmt>), // args, body
-    Args(Vec<(Symbol, Stmt)>),  // keyword args
+    Fn(Vec<Symbol>, Vec<Stmt>),                // args, body
+    Args(Vec<(Symbol, Stmt)>),                 // keyword args
+    Block(Symbol, Vec<Stmt>),                  // name, default body
+    Extends(Box<Stmt>),                        // layout name
+    Try(Vec<Stmt>, Option<Symbol>, Vec<Stmt>), // body, error var, rescue body
 }
 
 impl From<Tag> for Stmt {
@@ -38,6 +41,19 @@ impl From<&str> for Stmt {
 }
 
 impl Stmt {
+    /// Call the function named `name`.
+    pub fn call<S: Into<Symbol>>(name: S, args: Vec<Stmt>, span: Span) -> Stmt {
+        Stmt::Call(Box::new(Stmt::Word(name.into(), span)), args, span)
+    }
+
+    /// Where this Stmt is in the source, if we know.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Stmt::Word(_, span) | Stmt::Call(.., span) | Stmt::Assign(.., span) => Some(*span),
+            _ => None,
+        }
+    }
+
     /// Is this Stmt an actual statement?
     pub fn is_some(&self) -> bool {
         !self.is_none()
@@ -51,8 +67,8 @@ impl Stmt {
     /// If this is a String or a Word, get a &str of its literal value.
     pub fn to_str(&self) -> &str {
         match self {
-            Stmt::Value(Value::String(s)) => s.to_str(),
-            Stmt::Word(s) => s.to_str(),
+            Stmt::Value(Literal::String(s)) => s.to_str(),
+            Stmt::Word(s, _) => s.to_str(),
             _ => "",
         }
     }
@@ -61,9 +77,9 @@ impl Stmt {
     pub fn to_string(&self) -> String {
         match self {
             Stmt::None => "Stmt::None".to_string(),
-            Stmt::Value(Value::String(s)) => format!(r#""{}""#, s),
+            Stmt::Value(Literal::String(s)) => format!(r#""{}""#, s),
             Stmt::Value(v) => format!("{}", v),
-            Stmt::Word(s) => s.to_string(),
+            Stmt::Word(s, _) => s.to_string(),
             Stmt::Tag(tag) => format!("{:?}", tag),
             Stmt::Return(ex) => format!("return {:?}", ex),
             Stmt::Args(args) => args
@@ -81,18 +97,24 @@ impl Stmt {
             Stmt::Map(map) => format!(
                 "{{{}}}",
                 map.iter()
-                    .map(|(k, v)| format!("{}: {}", k.to_string(), v.to_string()))
+                    .map(|(k, v)| format!("{}: {}", k, v.to_string()))
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
-            Stmt::Assign(var, expr, re) => {
+            Stmt::Assign(var, expr, re, _) => {
                 format!("{} {}= {:?}", var, if *re { ":" } else { "" }, expr)
             }
             Stmt::If(..) => "IF: Coming Soon™".to_string(),
             Stmt::For(..) => "FOR: Coming Soon™".to_string(),
             Stmt::While(test, body) => format!("while({:?}) {:?}", test, body),
             Stmt::Fn(args, body) => format!("fn({:?}) {:?}", args, body),
-            Stmt::Call(name, args) => format!(
+            Stmt::Block(name, body) => format!("block {} {:?}", name, body),
+            Stmt::Extends(layout) => format!("extends {}", layout.to_string()),
+            Stmt::Try(body, var, rescue) => match var {
+                Some(var) => format!("try {:?} rescue {} {:?}", body, var, rescue),
+                None => format!("try {:?} rescue {:?}", body, rescue),
+            },
+            Stmt::Call(name, args, _) => format!(
                 "{}({})",
                 name.to_string(),
                 args.iter()
@@ -35,6 +35,10 @@ pub enum Syntax {
     While,
     In,
     Fn,
+    Block,
+    Extends,
+    Try,
+    Rescue,
 }
 
 impl Syntax {
@@ -51,7 +55,8 @@ impl Syntax {
     pub fn is_word_or_keyword(&self) -> bool {
         use Syntax::*;
         match self {
-            Word | Def | Do | Return | If | Then | Else | For | While | In | Fn => true,
+            Word | Def | Do | Return | If | Then | Else | For | While | In | Fn | Block
+            | Extends | Try | Rescue => true,
             None | Indent | Dedent | Bool(..) | Number | String(..) | JS | Op | Semi | Colon
             | Comma | LParen | RParen | LCurly | RCurly | LStaple | RStaple | LCaret | RCaret
             | Slash | Equal => false,
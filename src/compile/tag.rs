@@ -8,7 +8,7 @@ pub struct Tag {
     pub tag: Box<Stmt>,           // tag name
     pub id: Box<Stmt>,            // tag id
     pub classes: Vec<Stmt>,       // classes
-    pub attrs: Vec<(Stmt, Stmt)>, // other attributes
+    pub attrs: Vec<(Stmt, Stmt)>, // other attributes, or (None, map) to spread
     pub body: Vec<Stmt>,          // Bunch o' expressions
     pub closed: bool,             // <self-closing/> ?
 }
@@ -72,4 +72,10 @@ impl Tag {
     pub fn add_attr(&mut self, name: Stmt, val: Stmt) {
         self.attrs.push((name, val));
     }
+
+    /// Add a Map whose keys and values become attributes, like
+    /// `<input ...attrs>`. It goes in `attrs` with no name.
+    pub fn add_spread(&mut self, attrs: Stmt) {
+        self.attrs.push((Stmt::None, attrs));
+    }
 }
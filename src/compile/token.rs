@@ -1,4 +1,4 @@
-use crate::{Error, ErrorKind, Result, Symbol, Syntax};
+use crate::{Error, ErrorKind, Result, Span, Symbol, Syntax};
 
 /// A Token is an element of source code, a `Syntax` and its position.
 #[derive(Debug, Clone, Copy)]
@@ -7,6 +7,7 @@ pub struct Token<'s> {
     pub len: usize,
     pub kind: Syntax,
     lit: &'s str,
+    escapes: bool, // string with \n and friends
 }
 
 impl<'s> Token<'s> {
@@ -17,9 +18,21 @@ impl<'s> Token<'s> {
             pos,
             len,
             lit,
+            escapes: false,
         }
     }
 
+    /// Mark a string as having `\` escape codes, like `\n`.
+    pub fn with_escapes(mut self) -> Token<'s> {
+        self.escapes = true;
+        self
+    }
+
+    /// Does `literal()` have `\` escape codes?
+    pub fn has_escapes(&self) -> bool {
+        self.escapes
+    }
+
     /// Will it produce a `literal()`?
     pub fn is_literal(&self) -> bool {
         use Syntax::*;
@@ -27,11 +40,17 @@ impl<'s> Token<'s> {
         match self.kind {
             None | Bool(..) | Number | String(..) | Word | JS | Op | Colon | Comma | LParen
             | RParen | LCurly | RCurly | LStaple | RStaple | LCaret | RCaret | Slash | Equal
-            | Def | Do | Return | If | Then | Else | For | While | In | Fn => true,
+            | Def | Do | Return | If | Then | Else | For | While | In | Fn | Block | Extends
+            | Try | Rescue => true,
             Indent | Dedent | Semi => false,
         }
     }
 
+    /// Where this token is in the source.
+    pub fn span(&self) -> Span {
+        Span::new(self.pos, self.len)
+    }
+
     /// Get the literal value in source code, if it has one.
     pub fn literal(&self) -> &str {
         if self.is_literal() {
@@ -1,7 +1,7 @@
 #![allow(unused_macros)]
 use {
-    crate::Jump,
-    std::{error, fmt, io, num},
+    crate::{Jump, Span},
+    std::{convert::Infallible, error, fmt, io, num, sync::Arc},
 };
 
 /// What kind of error?
@@ -24,6 +24,44 @@ pub struct Error {
     pub details: String,
     pub pos: usize,
     pub len: usize,
+    pub file: Option<Arc<Source>>, // the template `pos` is in
+    pub stack: Vec<Frame>,         // innermost call first
+}
+
+/// The name and source code of a template, so errors can show where
+/// they happened on their own.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Source {
+    pub name: Option<String>,
+    pub code: String,
+}
+
+/// A Hatter function that was running, or an `include()` or
+/// `import()` that was loading a template, when an error happened.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub name: String,
+    pub file: Option<Arc<Source>>,
+    pub pos: usize, // call site
+    pub len: usize,
+}
+
+impl Source {
+    /// Sources are shared by every error and frame that points into
+    /// them.
+    pub fn new(name: Option<&str>, code: &str) -> Arc<Source> {
+        Arc::new(Source {
+            name: name.map(String::from),
+            code: code.into(),
+        })
+    }
+}
+
+impl Frame {
+    /// Is this where a template was included or imported?
+    pub fn is_include(&self) -> bool {
+        matches!(self.name.as_str(), "include" | "import")
+    }
 }
 
 impl Error {
@@ -33,8 +71,148 @@ impl Error {
             details,
             pos,
             len,
+            file: None,
+            stack: vec![],
         }
     }
+
+    /// Point the error at `span`, unless it already knows where it
+    /// happened. If it came from an included template, `span` is
+    /// where that template was included.
+    pub fn at(mut self, span: Span) -> Error {
+        if matches!(self.kind, ErrorKind::Jump(..)) {
+            return self;
+        }
+        if let Some(frame) = self.stack.last_mut().filter(|f| f.pos == 0 && f.len == 0) {
+            frame.pos = span.pos;
+            frame.len = span.len;
+        } else if self.pos == 0 && self.len == 0 {
+            self.pos = span.pos;
+            self.len = span.len;
+        }
+        self
+    }
+
+    /// Record that the error happened inside a call to the Hatter
+    /// function `name` at `span`.
+    pub fn called_from(mut self, name: &str, span: Span) -> Error {
+        self.stack.push(Frame {
+            name: name.to_string(),
+            file: None,
+            pos: span.pos,
+            len: span.len,
+        });
+        self
+    }
+
+    /// Record that the error happened in a template loaded by
+    /// `include()` or `import()`. The next `at()` says where.
+    pub fn included(self, how: &str) -> Error {
+        if matches!(self.kind, ErrorKind::Jump(..)) {
+            return self;
+        }
+        self.called_from(how, Span::default())
+    }
+
+    /// The error is leaving the template `file`, so anything that
+    /// doesn't know which template it's in must be in this one.
+    pub fn in_source(mut self, file: &Arc<Source>) -> Error {
+        if matches!(self.kind, ErrorKind::Jump(..)) {
+            return self;
+        }
+        if self.file.is_none() {
+            self.file = Some(file.clone());
+        }
+        for frame in &mut self.stack {
+            if frame.file.is_none() {
+                frame.file = Some(file.clone());
+            }
+        }
+        self
+    }
+
+    /// Where the template was included from, innermost first.
+    pub fn includes(&self) -> impl Iterator<Item = &Frame> {
+        self.stack.iter().filter(|f| f.is_include())
+    }
+
+    /// Write the error with the line it happened on, like
+    /// `print_error`. Templates without a name are called `default`.
+    fn report(&self, f: &mut dyn fmt::Write, default: &str, color: bool) -> fmt::Result {
+        let (red, blue, clear) = if color {
+            ("\x1b[91m", "\x1b[1;94m", "\x1b[0m")
+        } else {
+            ("", "", "")
+        };
+        let name = |file: &Option<Arc<Source>>| match file.as_deref() {
+            Some(Source {
+                name: Some(name), ..
+            }) => name.clone(),
+            _ => default.to_string(),
+        };
+        let source = self.file.as_ref().map(|f| &*f.code).unwrap_or("");
+
+        let (line, col) = line_and_col(source, self.pos);
+        writeln!(
+            f,
+            " {}-->{} {}:{}:{}",
+            blue,
+            clear,
+            name(&self.file),
+            line,
+            col
+        )?;
+        writeln!(f, "   {}|{}", blue, clear)?;
+
+        let lines = source.split('\n').collect::<Vec<_>>();
+        let pline = |f: &mut dyn fmt::Write, num: usize| match lines.get(num) {
+            Some(line) => writeln!(f, "{}   |{} {}", blue, clear, line),
+            None => Ok(()),
+        };
+
+        if line > 2 {
+            pline(f, line - 3)?;
+        }
+        if line > 1 {
+            pline(f, line - 2)?;
+        }
+        writeln!(
+            f,
+            "{}{: <3}|{} {}",
+            blue,
+            line,
+            clear,
+            lines.get(line - 1).unwrap_or(&"")
+        )?;
+        writeln!(
+            f,
+            "   {}|{} {}{} {}{}",
+            blue,
+            red,
+            " ".repeat(if col > 0 { col - 1 } else { 0 }),
+            "^".repeat(std::cmp::min(self.len, 20)),
+            self.details,
+            clear
+        )?;
+        pline(f, line)?;
+        pline(f, line + 1)?;
+
+        for frame in &self.stack {
+            let source = frame.file.as_ref().map(|f| &*f.code).unwrap_or("");
+            let (line, col) = line_and_col(source, frame.pos);
+            let place = format!("{}:{}:{}", name(&frame.file), line, col);
+            if frame.is_include() {
+                writeln!(f, "   {}={} {}d from {}", blue, clear, frame.name, place)?;
+            } else {
+                writeln!(
+                    f,
+                    "   {}={} in {}(), called at {}",
+                    blue, clear, frame.name, place
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl error::Error for Error {
@@ -43,9 +221,15 @@ impl error::Error for Error {
     }
 }
 
+/// Shows the line the error happened on if it knows its template,
+/// just like `print_error`, but without colors.
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Error: {}", self.details)
+        if self.file.is_some() {
+            self.report(f, "(template)", false)
+        } else {
+            write!(f, "Error: {}", self.details)
+        }
     }
 }
 
@@ -56,6 +240,8 @@ impl From<num::ParseIntError> for Error {
             details: format!("{}", error),
             pos: 0,
             len: 0,
+            file: None,
+            stack: vec![],
         }
     }
 }
@@ -67,13 +253,21 @@ impl From<io::Error> for Error {
             details: format!("{}", error),
             pos: 0,
             len: 0,
+            file: None,
+            stack: vec![],
         }
     }
 }
 
+impl From<Infallible> for Error {
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
 impl From<Error> for io::Error {
     fn from(error: Error) -> Self {
-        io::Error::new(io::ErrorKind::Other, error.details)
+        io::Error::other(error.details)
     }
 }
 
@@ -84,7 +278,7 @@ macro_rules! scan_error {
         Err(Error::new(ErrorKind::SyntaxError, $msg.into(), $pos, $len))
     }};
     ($pos:expr, $len:expr, $msg:expr, $($args:expr),+) => {
-        scan_error!($pos, $len, format!($msg, $($args),*));
+        scan_error!($pos, $len, format!($msg, $($args),*))
     };
 }
 
@@ -95,7 +289,7 @@ macro_rules! pos_error {
         Err(Error::new(ErrorKind::ParseError, $msg.into(), $pos, 1))
     }};
     ($pos:expr, $msg:expr, $($args:expr),+) => {
-        pos_error!($pos, format!($msg, $($args),+));
+        pos_error!($pos, format!($msg, $($args),+))
     };
 }
 
@@ -114,7 +308,7 @@ macro_rules! error_kind {
         Error::new(ErrorKind::$kind, $msg.into(), 0, 0)
     }};
     ($kind:ident, $msg:expr, $($args:expr),*) => {
-        error_kind!($kind, format!($msg, $($args),*));
+        error_kind!($kind, format!($msg, $($args),*))
     };
 }
 
@@ -125,64 +319,20 @@ macro_rules! error {
         Err(Error::new(ErrorKind::RuntimeError, $msg.into(), 0, 0))
     }};
     ($msg:expr, $($args:expr),*) => {
-        error!(format!($msg, $($args),*));
+        error!(format!($msg, $($args),*))
     };
 }
 
-/// Pretty-print an error message, complete with colors and the line
-/// in question.
+/// Pretty-print an error message to stderr, complete with colors and
+/// the line in question. Errors from other templates, like ones
+/// loaded with `include()`, show their own source instead of
+/// `source`.
 pub fn print_error<P: AsRef<std::path::Path>, S: AsRef<str>>(path: P, source: S, err: Error) {
-    let path = path.as_ref();
-    let source = source.as_ref();
-    let (red, blue, _gold, clear) = if std::env::var("NO_COLOR").is_ok() {
-        ("", "", "", "")
-    } else {
-        ("\x1b[91m", "\x1b[1;94m", "\x1b[1;93m", "\x1b[0m")
-    };
-
-    let (line, col) = line_and_col(source, err.pos);
-    // println!("(line, col) = {:?}", (line, col));
-    println!(
-        " {}-->{} {}:{}:{}",
-        blue,
-        clear,
-        path.to_str().unwrap(),
-        line,
-        col
-    );
-    println!("   {}|{}", blue, clear);
-
-    let lines = source.split('\n');
-    let pline = |num| {
-        if let Some(line) = lines.clone().nth(num) {
-            println!("{}   |{} {}", blue, clear, line)
-        }
-    };
-
-    if line > 2 {
-        pline(line - 3);
-    }
-    if line > 1 {
-        pline(line - 2);
-    }
-    println!(
-        "{}{: <3}|{} {}",
-        blue,
-        line,
-        clear,
-        lines.clone().nth(line - 1).unwrap()
-    );
-    println!(
-        "   {}|{} {}{} {}{}",
-        blue,
-        red,
-        " ".repeat(if col > 0 { col - 1 } else { 0 }),
-        "^".repeat(std::cmp::min(err.len, 20)),
-        err.details,
-        clear
-    );
-    pline(line);
-    pline(line + 1);
+    let path = path.as_ref().to_string_lossy();
+    let err = err.in_source(&Source::new(None, source.as_ref()));
+    let mut out = String::new();
+    let _ = err.report(&mut out, &path, std::env::var("NO_COLOR").is_err());
+    eprint!("{}", out);
 }
 
 /// Calculate line # and col position for a position in a source file.
@@ -1,5 +1,5 @@
 use {
-    crate::{Env, Result, Value},
+    crate::{value::Map, Env, Result, Value},
     std::{ops, vec},
 };
 
@@ -43,11 +43,10 @@ impl<'e> Args<'e> {
 
     /// Like `get()` but returns an error.
     pub fn need(&self, idx: usize) -> Result<Value> {
-        self.args.get(idx).map(Value::clone).ok_or(error_kind!(
-            ArgNotFound,
-            "Argument Not Found: {}",
-            idx
-        ))
+        self.args
+            .get(idx)
+            .cloned()
+            .ok_or(error_kind!(ArgNotFound, "Argument Not Found: {}", idx))
     }
 
     /// Errors if the arg doesn't exist or isn't a Number.
@@ -65,7 +64,7 @@ impl<'e> Args<'e> {
 
     /// Errors if the arg doesn't exist or isn't a String.
     pub fn need_string(&self, idx: usize) -> Result<&str> {
-        if let Some(Value::String(s)) = self.args.get(idx) {
+        if let Some(Value::String(s) | Value::Safe(s)) = self.args.get(idx) {
             Ok(s.to_str())
         } else {
             Err(error_kind!(
@@ -88,4 +87,25 @@ impl<'e> Args<'e> {
             ))
         }
     }
+
+    /// Like `need_list()`, but returns a copy of the List's items.
+    pub fn need_vec(&self, idx: usize) -> Result<Vec<Value>> {
+        match self.need_list(idx)? {
+            Value::List(list) => Ok(list.borrow().clone()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Errors if the arg doesn't exist or isn't a Map.
+    pub fn need_map(&self, idx: usize) -> Result<Map> {
+        if let Some(Value::Map(map)) = self.args.get(idx) {
+            Ok(map.clone())
+        } else {
+            Err(error_kind!(
+                WrongArgType,
+                "Expected Map, got: {:?}",
+                self.need(idx)?
+            ))
+        }
+    }
 }
@@ -13,8 +13,11 @@
 // other applications, too.
 
 use {
-    crate::{Args, Env, Native, Result, Special, Stmt, Symbol, Value},
-    std::{collections::HashMap, rc::Rc},
+    crate::{
+        escape_html, parse_json, to_json, Args, Env, ErrorKind, Native, OMap, Result, Special,
+        Stmt, Symbol, Value,
+    },
+    std::{cmp::Ordering, collections::HashMap, rc::Rc},
 };
 
 /// Return the builtin Special functions that come with Hatter.
@@ -25,6 +28,7 @@ pub(crate) fn specials() -> HashMap<String, Rc<Special>> {
 
     map.insert("&&".into(), rc!(and));
     map.insert("||".into(), rc!(or));
+    map.insert("try".into(), rc!(r#try));
 
     map
 }
@@ -40,6 +44,7 @@ pub(crate) fn natives() -> HashMap<String, Rc<Native>> {
     }
 
     native!("type" => r#type);
+    native!("error" => error);
     native!("when" => when);
     native!("==" => eq);
     native!("!=" => neq);
@@ -51,7 +56,29 @@ pub(crate) fn natives() -> HashMap<String, Rc<Native>> {
     native!("<<" => push);
     native!("push" => push);
     native!("pop" => pop);
-    native!("." => index);
+    native!("map" => self::map);
+    native!("filter" => filter);
+    native!("reduce" => reduce);
+    native!("sort" => sort);
+    native!("sort-by" => sort_by);
+    native!("reverse" => reverse);
+    native!("uniq" => uniq);
+    native!("group-by" => group_by);
+    native!("find" => find);
+    native!("any?" => any_);
+    native!("all?" => all_);
+    native!("first" => first);
+    native!("last" => last);
+    native!("take" => take);
+    native!("drop" => drop);
+    native!("zip" => zip);
+    native!("flatten" => flatten);
+    native!("keys" => keys);
+    native!("values" => values);
+    native!("merge" => merge);
+    native!("has-key?" => has_key_);
+    native!("remove" => remove);
+    native!("." => dot);
     native!(".." => range);
     native!("..=" => range_inclusive);
     native!("+" => add);
@@ -65,14 +92,37 @@ pub(crate) fn natives() -> HashMap<String, Rc<Native>> {
     native!("<=" => lte);
     native!("print" => print);
     native!("puts" => puts);
+    native!("include" => include);
+    native!("import" => import);
     native!("to-uppercase" => to_uppercase);
     native!("to-lowercase" => to_lowercase);
     native!("replace" => replace);
     native!("contains?" => contains_);
     native!("split" => split);
     native!("count" => count);
+    native!("trim" => trim);
+    native!("ltrim" => ltrim);
+    native!("rtrim" => rtrim);
+    native!("starts-with?" => starts_with_);
+    native!("ends-with?" => ends_with_);
+    native!("join" => join);
+    native!("capitalize" => capitalize);
+    native!("titlecase" => titlecase);
+    native!("truncate" => truncate);
+    native!("pad-left" => pad_left);
+    native!("pad-right" => pad_right);
+    native!("repeat" => repeat);
+    native!("slice" => slice);
+    native!("lines" => lines);
+    native!("slugify" => slugify);
+    native!("strip-tags" => strip_tags);
+    native!("wordwrap" => wordwrap);
     native!("len" => len);
     native!("empty?" => empty_);
+    native!("raw" => raw);
+    native!("escape" => escape);
+    native!("to-json" => to_json_);
+    native!("parse-json" => parse_json_);
 
     map
 }
@@ -84,12 +134,25 @@ pub(crate) fn natives() -> HashMap<String, Rc<Native>> {
 /// Used internally by String interpolation.
 ///
 /// `concat("hi", "-", 23) #=> "hi-23"`
+/// If any of the Values are safe, the others are escaped and the
+/// result is safe too.
 pub fn concat(args: Args) -> Result<Value> {
+    if !args.iter().any(|arg| matches!(arg, Value::Safe(..))) {
+        let mut sum = String::new();
+        for arg in args {
+            sum.push_str(&arg.to_string());
+        }
+        return Ok(sum.into());
+    }
+
     let mut sum = String::new();
     for arg in args {
-        sum.push_str(&arg.to_string());
+        match arg {
+            Value::Safe(s) => sum.push_str(&s),
+            arg => sum.push_str(&escape_html(&arg.to_string())),
+        }
     }
-    Ok(sum.into())
+    Ok(Value::Safe(sum.into()))
 }
 
 /// Returns a Value if a condition is true.
@@ -118,6 +181,42 @@ pub fn r#type(args: Args) -> Result<Value> {
     Value::String(args.need(0)?.typename().into()).ok()
 }
 
+/// Raise an error, stopping the program unless it's caught by
+/// `try()` or a `try` block.
+///
+/// `error("Panic!")`
+pub fn error(args: Args) -> Result<Value> {
+    error!("{}", args.need(0)?)
+}
+
+/// Special: Call a function, catching any error it raises. Returns
+/// a Map with either the function's return value in `ok` or the
+/// error message in `err`.
+///
+/// ```ignore
+/// ret := try(combine, "Mr.", "Robinson Crusoe")
+/// if ret.err
+///     print(ret.err)
+/// ```
+pub fn r#try(env: &mut Env, args: &[Stmt]) -> Result<Value> {
+    let (f, args) = match args.split_first() {
+        Some(split) => split,
+        None => return error!("Expected at least 1 arg, got 0"),
+    };
+    let call = Stmt::Call(
+        Box::new(f.clone()),
+        args.to_vec(),
+        f.span().unwrap_or_default(),
+    );
+    let mut map = OMap::new();
+    match env.attempt(|env| env.eval(&call)) {
+        Ok(val) => map.insert("ok", val),
+        Err(e) if matches!(e.kind, ErrorKind::Jump(..)) => return Err(e),
+        Err(e) => map.insert("err", e.details.into()),
+    }
+    Ok(Value::Map(map.into()))
+}
+
 //////////////////////////////////////////////////////////////////////
 // Boolean Operators
 
@@ -150,8 +249,8 @@ pub fn eq(args: Args) -> Result<Value> {
                 Some(Value::Number(n2)) => n1 == n2,
                 _ => false,
             },
-            Value::String(s1) => match args.get(1) {
-                Some(Value::String(s2)) => s1 == s2,
+            Value::String(s1) | Value::Safe(s1) => match args.get(1) {
+                Some(Value::String(s2) | Value::Safe(s2)) => s1 == s2,
                 _ => false,
             },
             _ => false,
@@ -208,7 +307,9 @@ pub fn lte(args: Args) -> Result<Value> {
 //////////////////////////////////////////////////////////////////////
 // Math Functions
 
-/// `+` operator: add two numbers.
+/// `+` operator: add two numbers, or join two strings. Like
+/// `concat()`, adding a safe string escapes the others and returns a
+/// safe string.
 pub fn add(args: Args) -> Result<Value> {
     if let Some(Value::Number(_)) = args.get(0) {
         let mut sum = 0.0;
@@ -217,13 +318,27 @@ pub fn add(args: Args) -> Result<Value> {
             sum += x;
         }
         return Value::Number(sum).ok();
-    } else if let Some(Value::String(_)) = args.get(0) {
+    } else if let Some(Value::String(_) | Value::Safe(_)) = args.get(0) {
+        let strs = args
+            .iter()
+            .take_while(|x| matches!(x, Value::String(_) | Value::Safe(_)))
+            .collect::<Vec<_>>();
+        if !strs.iter().any(|x| matches!(x, Value::Safe(_))) {
+            let mut sum = String::new();
+            for x in strs {
+                sum.push_str(x.to_str());
+            }
+            return Value::String(sum.into()).ok();
+        }
+
         let mut sum = String::new();
-        let mut iter = args.iter();
-        while let Some(Value::String(x)) = iter.next() {
-            sum += x;
+        for x in strs {
+            match x {
+                Value::Safe(s) => sum.push_str(s.to_str()),
+                x => sum.push_str(&escape_html(x.to_str())),
+            }
         }
-        return Value::String(sum.into()).ok();
+        return Value::Safe(sum.into()).ok();
     }
     Value::None.ok()
 }
@@ -294,9 +409,374 @@ pub fn replace(args: Args) -> Result<Value> {
     Value::String(s.replace(search, replace).into()).ok()
 }
 
+/// The optional String arg at `idx`, or `default`.
+fn string_or<'a>(args: &'a Args, idx: usize, default: &'a str) -> Result<&'a str> {
+    match args.get(idx) {
+        Some(..) => args.need_string(idx),
+        None => Ok(default),
+    }
+}
+
+/// Remove whitespace, or any of the chars in the optional second
+/// arg, from both ends of a string.
+///
+/// `trim("  hi  ") #=> "hi"`
+/// `trim("--hi--", "-") #=> "hi"`
+pub fn trim(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let trimmed = match args.get(1) {
+        Some(..) => {
+            let set = args.need_string(1)?;
+            s.trim_matches(|c| set.contains(c))
+        }
+        None => s.trim(),
+    };
+    Value::from(trimmed).ok()
+}
+
+/// Like `trim()`, but only the start of the string.
+///
+/// `ltrim("  hi  ") #=> "hi  "`
+pub fn ltrim(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let trimmed = match args.get(1) {
+        Some(..) => {
+            let set = args.need_string(1)?;
+            s.trim_start_matches(|c| set.contains(c))
+        }
+        None => s.trim_start(),
+    };
+    Value::from(trimmed).ok()
+}
+
+/// Like `trim()`, but only the end of the string.
+///
+/// `rtrim("  hi  ") #=> "  hi"`
+pub fn rtrim(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let trimmed = match args.get(1) {
+        Some(..) => {
+            let set = args.need_string(1)?;
+            s.trim_end_matches(|c| set.contains(c))
+        }
+        None => s.trim_end(),
+    };
+    Value::from(trimmed).ok()
+}
+
+/// Does the string start with a prefix?
+/// `starts-with?("Mr Rogers", "Mr") #=> true`
+pub fn starts_with_(args: Args) -> Result<Value> {
+    Value::Bool(args.need_string(0)?.starts_with(args.need_string(1)?)).ok()
+}
+
+/// Does the string end with a suffix?
+/// `ends-with?("Mr Rogers", "ers") #=> true`
+pub fn ends_with_(args: Args) -> Result<Value> {
+    Value::Bool(args.need_string(0)?.ends_with(args.need_string(1)?)).ok()
+}
+
+/// Combine the items in a List into a String, with an optional
+/// separator between them.
+///
+/// `join(["a", "b", 3], ", ") #=> "a, b, 3"`
+pub fn join(args: Args) -> Result<Value> {
+    let sep = string_or(&args, 1, "")?;
+    let joined = args
+        .need_vec(0)?
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(sep);
+    Value::from(joined).ok()
+}
+
+/// Uppercase the first letter and lowercase the rest.
+/// `capitalize("hELLO there") #=> "Hello there"`
+pub fn capitalize(args: Args) -> Result<Value> {
+    Value::from(capitalize_word(args.need_string(0)?)).ok()
+}
+
+fn capitalize_word(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Capitalize every word.
+/// `titlecase("the WIND in the willows") #=> "The Wind In The Willows"`
+pub fn titlecase(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let mut out = String::with_capacity(s.len());
+    let mut word = String::new();
+    for c in s.chars() {
+        if c.is_whitespace() {
+            out.push_str(&capitalize_word(&word));
+            out.push(c);
+            word.clear();
+        } else {
+            word.push(c);
+        }
+    }
+    out.push_str(&capitalize_word(&word));
+    Value::from(out).ok()
+}
+
+/// Shorten a string to at most `len` chars, ending it with an
+/// ellipsis (`...` or the optional third arg) if it was cut.
+///
+/// `truncate("Once upon a time", 10) #=> "Once up..."`
+/// `truncate("Once upon a time", 10, "…") #=> "Once upon…"`
+pub fn truncate(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let len = args.need_number(1)?.max(0.0) as usize;
+    let ellipsis = string_or(&args, 2, "...")?;
+    if s.chars().count() <= len {
+        return Value::from(s).ok();
+    }
+    let keep = len.saturating_sub(ellipsis.chars().count());
+    let mut out = s
+        .chars()
+        .take(keep)
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+    out.extend(ellipsis.chars().take(len));
+    Value::from(out).ok()
+}
+
+/// Fill the start of a string with spaces, or the optional third
+/// arg, until it's `width` chars long.
+///
+/// `pad-left("7", 3, "0") #=> "007"`
+pub fn pad_left(args: Args) -> Result<Value> {
+    let (s, pad) = pad_with(&args)?;
+    Value::from(pad + s).ok()
+}
+
+/// Fill the end of a string with spaces, or the optional third arg,
+/// until it's `width` chars long.
+///
+/// `pad-right("ab", 4, ".") #=> "ab.."`
+pub fn pad_right(args: Args) -> Result<Value> {
+    let (s, pad) = pad_with(&args)?;
+    Value::from(s.to_string() + &pad).ok()
+}
+
+/// The string to pad and the padding it needs.
+fn pad_with<'a>(args: &'a Args) -> Result<(&'a str, String)> {
+    let s = args.need_string(0)?;
+    let width = args.need_number(1)?.max(0.0) as usize;
+    let fill = string_or(args, 2, " ")?;
+    let missing = width.saturating_sub(s.chars().count());
+    check_len(fill.len().max(1), missing)?;
+    Ok((s, fill.chars().cycle().take(missing).collect()))
+}
+
+/// Repeat a string `n` times.
+/// `repeat("ab", 3) #=> "ababab"`
+pub fn repeat(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let n = args.need_number(1)?.max(0.0) as usize;
+    check_len(s.len(), n)?;
+    Value::from(s.repeat(n)).ok()
+}
+
+/// Longest string, in bytes, that `repeat()` and `pad-left()` or
+/// `pad-right()` will build.
+const MAX_LEN: usize = 64 * 1024 * 1024;
+
+/// Make sure `n` copies of `len` bytes isn't too long to build.
+fn check_len(len: usize, n: usize) -> Result<()> {
+    match len.checked_mul(n) {
+        Some(total) if total <= MAX_LEN => Ok(()),
+        _ => error!("String too long: {} x {} bytes", n, len),
+    }
+}
+
+/// Part of a String or List, from `start` up to but not including
+/// the optional `end`. Negative numbers count back from the end.
+///
+/// `slice("héllo", 1, 3) #=> "él"`
+/// `slice([1, 2, 3, 4], -2) #=> [3, 4]`
+pub fn slice(args: Args) -> Result<Value> {
+    let range = |len: usize| -> Result<(usize, usize)> {
+        let idx = |i: f64| {
+            if i < 0.0 {
+                len.saturating_sub(i.abs() as usize)
+            } else {
+                (i as usize).min(len)
+            }
+        };
+        let start = idx(args.need_number(1)?);
+        let end = match args.get(2) {
+            Some(..) => idx(args.need_number(2)?),
+            None => len,
+        };
+        Ok((start, end.max(start)))
+    };
+    match args.need(0)? {
+        Value::List(list) => {
+            let list = list.borrow();
+            let (start, end) = range(list.len())?;
+            Value::from(list[start..end].to_vec()).ok()
+        }
+        _ => {
+            let s = args.need_string(0)?;
+            let (start, end) = range(s.chars().count())?;
+            Value::from(s.chars().skip(start).take(end - start).collect::<String>()).ok()
+        }
+    }
+}
+
+/// Split a string into a List of its lines.
+/// `lines("one\ntwo\r\nthree") #=> ["one", "two", "three"]`
+pub fn lines(args: Args) -> Result<Value> {
+    Value::from(args.need_string(0)?.lines().collect::<Vec<_>>()).ok()
+}
+
+/// Turn a string into something fit for a URL: lowercase letters
+/// and numbers, with dashes in between.
+///
+/// `slugify("Hello, Wörld! 2") #=> "hello-wörld-2"`
+pub fn slugify(args: Args) -> Result<Value> {
+    let mut out = String::new();
+    for c in args.need_string(0)?.chars() {
+        if c.is_alphanumeric() {
+            out.extend(c.to_lowercase());
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    Value::from(out.trim_end_matches('-')).ok()
+}
+
+/// Remove HTML tags and comments from a string, keeping the text.
+///
+/// `strip-tags("<p>Hi <b>there</b></p>") #=> "Hi there"`
+pub fn strip_tags(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('<') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let is_tag = rest[1..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '/' || c == '!');
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|e| e + 3)
+        } else {
+            rest.find('>').map(|e| e + 1)
+        };
+        match end {
+            Some(end) if is_tag => rest = &rest[end..],
+            _ => {
+                out.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Value::from(out).ok()
+}
+
+/// Wrap text so no line is longer than `width` chars, breaking
+/// between words. Words longer than `width` get their own line. The
+/// optional third arg is put between lines instead of `\n`.
+///
+/// `wordwrap("The quick brown fox", 10) #=> "The quick\nbrown fox"`
+pub fn wordwrap(args: Args) -> Result<Value> {
+    let s = args.need_string(0)?;
+    let width = args.need_number(1)?.max(1.0) as usize;
+    let newline = string_or(&args, 2, "\n")?;
+    let mut out = vec![];
+    for line in s.lines() {
+        let mut cur = String::new();
+        let mut cur_len = 0;
+        for word in line.split_whitespace() {
+            let len = word.chars().count();
+            if cur_len > 0 && cur_len + 1 + len > width {
+                out.push(std::mem::take(&mut cur));
+                cur_len = 0;
+            }
+            if cur_len > 0 {
+                cur.push(' ');
+                cur_len += 1;
+            }
+            cur.push_str(word);
+            cur_len += len;
+        }
+        out.push(cur);
+    }
+    Value::from(out.join(newline)).ok()
+}
+
+/// Mark a Value as safe, so it won't be HTML escaped when printed.
+///
+/// ```ignore
+/// html := "<b>Bold</b>"
+/// <p> raw(html)    #=> <p><b>Bold</b></p>
+/// ```
+pub fn raw(args: Args) -> Result<Value> {
+    Value::Safe(args.need(0)?.to_string().into()).ok()
+}
+
+/// HTML escape a Value, returning a safe String.
+///
+/// `escape("<b>Bold</b>") #=> "&lt;b&gt;Bold&lt;/b&gt;"`
+pub fn escape(args: Args) -> Result<Value> {
+    Value::Safe(escape_html(&args.need(0)?.to_string()).into()).ok()
+}
+
+//////////////////////////////////////////////////////////////////////
+// JSON Functions
+
+/// Convert a Value to JSON. The result is safe to print inside a
+/// `<script>` tag.
+///
+/// ```ignore
+/// to-json({ name: "Ra", tags: [1, 2] })  #=> {"name":"Ra","tags":[1,2]}
+/// <script> "var PAGES = {pages.json};"
+/// ```
+pub fn to_json_(args: Args) -> Result<Value> {
+    Value::Safe(to_json(&args.need(0)?)?.into()).ok()
+}
+
+/// Parse a JSON String into a Value.
+///
+/// `parse-json('[1, "two", null]') #=> [1, "two", None]`
+pub fn parse_json_(args: Args) -> Result<Value> {
+    parse_json(args.need_string(0)?)
+}
+
 //////////////////////////////////////////////////////////////////////
 // Container (Map, List, Object) Functions
 
+/// `.` operator
+/// Like `index()`, but `value.json` converts any Value to JSON unless
+/// it's a Map or Object with its own `json` key.
+pub fn dot(args: Args) -> Result<Value> {
+    if args.len() == 2 && args.need(1)?.to_str() == "json" {
+        let own = match args.need(0)? {
+            Value::Map(map) => map.borrow().get("json").cloned(),
+            Value::Object(o) => o.get("json"),
+            _ => None,
+        };
+        return match own {
+            Some(val) => val.ok(),
+            None => to_json_(args),
+        };
+    }
+    index(args)
+}
+
 /// `.` operator and `[]` operator
 /// Look up a List item by number or a Map item by key.
 /// Returns None or the Value.
@@ -316,7 +796,7 @@ pub fn index(args: Args) -> Result<Value> {
             let mut idx = args.need_number(1)? as isize;
             if idx < 0 {
                 let len = list.borrow().len();
-                if (idx.abs() as usize) <= len {
+                if idx.unsigned_abs() <= len {
                     idx += list.borrow().len() as isize;
                 }
             }
@@ -342,13 +822,13 @@ fn set_index(args: Args) -> Result<Value> {
     match args.need(0)? {
         Value::Map(map) => {
             map.borrow_mut()
-                .insert(Symbol::from(args.need_string(1)?), args.need(2)?.into());
+                .insert(Symbol::from(args.need_string(1)?), args.need(2)?);
         }
         Value::List(list) => {
             let mut idx = args.need_number(1)? as isize;
             if idx < 0 {
                 let len = list.borrow().len();
-                if (idx.abs() as usize) < len {
+                if idx.unsigned_abs() < len {
                     idx += list.borrow().len() as isize;
                 }
             }
@@ -356,7 +836,7 @@ fn set_index(args: Args) -> Result<Value> {
             if idx > list.borrow().len() {
                 return Value::None.ok();
             }
-            list.borrow_mut().insert(idx, args.need(2)?.into());
+            list.borrow_mut().insert(idx, args.need(2)?);
         }
         Value::Object(o) => {
             o.set(args.need_string(1)?, args.need(2)?);
@@ -366,23 +846,18 @@ fn set_index(args: Args) -> Result<Value> {
     Value::None.ok()
 }
 
-/// Get the length of a Map, List, or String. Returns 0 for all
-/// other values.
+/// Get the length of a Map, List, or String. Strings count chars,
+/// like `slice()` and friends. Returns 0 for all other values.
 ///
 /// ```ignore
 /// len([])            #=> 0
 /// len([5])           #=> 1
 /// len('hi')          #=> 2
+/// len('héllo')       #=> 5
 /// len({name:'Ra'})   #=> 1
 /// ```
 pub fn len(args: Args) -> Result<Value> {
-    match args.need(0)? {
-        Value::List(list) => list.borrow().len().into(),
-        Value::Map(map) => map.borrow().len().into(),
-        Value::String(s) => s.len().into(),
-        _ => Value::Number(0.0),
-    }
-    .ok()
+    Value::from(args.need(0)?.len()).ok()
 }
 
 /// True if the length of a Map, List, or String is `0`.
@@ -427,6 +902,412 @@ pub fn pop(args: Args) -> Result<Value> {
     .ok()
 }
 
+/// Call a function with each item in a List and return a List of
+/// the results. With a Map, the function gets each key and value.
+///
+/// ```ignore
+/// map([1, 2, 3], fn(x) x * 2)                 #=> [2, 4, 6]
+/// map({ a: 1 }, fn(k, v) concat(k, ':', v))   #=> ["a:1"]
+/// ```
+pub fn map(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    let mut out = vec![];
+    match args.need(0)? {
+        Value::Map(map) => {
+            let entries = map
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>();
+            for (k, v) in entries {
+                out.push(args.env.call(&f, vec![k.into(), v])?);
+            }
+        }
+        _ => {
+            for item in args.need_vec(0)? {
+                out.push(args.env.call(&f, vec![item])?);
+            }
+        }
+    }
+    Value::from(out).ok()
+}
+
+/// Keep only the items of a List the function returns true for.
+/// With a Map, the function gets each key and value and a new Map is
+/// returned.
+///
+/// ```ignore
+/// filter(1..=5, fn(x) x % 2 == 0)          #=> [2, 4]
+/// filter({ a: 1, b: 2 }, fn(k, v) v > 1)   #=> { b: 2 }
+/// ```
+pub fn filter(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    match args.need(0)? {
+        Value::Map(map) => {
+            let entries = map
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>();
+            let mut out = OMap::new();
+            for (k, v) in entries {
+                if args
+                    .env
+                    .call(&f, vec![k.clone().into(), v.clone()])?
+                    .to_bool()
+                {
+                    out.insert(k, v);
+                }
+            }
+            Value::Map(out.into()).ok()
+        }
+        _ => {
+            let mut out = vec![];
+            for item in args.need_vec(0)? {
+                if args.env.call(&f, vec![item.clone()])?.to_bool() {
+                    out.push(item);
+                }
+            }
+            Value::from(out).ok()
+        }
+    }
+}
+
+/// Combine the items of a List into one Value by calling the function
+/// with the result so far and each item. Starts with `init`, or the
+/// first item if there's no `init`.
+///
+/// ```ignore
+/// reduce([1, 2, 3], fn(sum, x) sum + x)      #=> 6
+/// reduce(['a', 'b'], fn(s, x) s + x, '>')    #=> ">ab"
+/// ```
+pub fn reduce(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    let mut items = args.need_vec(0)?.into_iter();
+    let mut acc = match args.get(2) {
+        Some(init) => init.clone(),
+        None => match items.next() {
+            Some(first) => first,
+            None => return Value::None.ok(),
+        },
+    };
+    for item in items {
+        acc = args.env.call(&f, vec![acc, item])?;
+    }
+    acc.ok()
+}
+
+/// Order Values for `sort()`: numbers and strings in their natural
+/// order, everything else by type and then by how it prints.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Value::String(a) | Value::Safe(a), Value::String(b) | Value::Safe(b)) => {
+            a.to_str().cmp(b.to_str())
+        }
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        _ => a
+            .typename()
+            .cmp(b.typename())
+            .then_with(|| a.to_string().cmp(&b.to_string())),
+    }
+}
+
+/// Return a sorted copy of a List.
+///
+/// ```ignore
+/// sort([3, 1, 2])        #=> [1, 2, 3]
+/// sort(['b', 'c', 'a'])  #=> ["a", "b", "c"]
+/// ```
+pub fn sort(args: Args) -> Result<Value> {
+    let mut items = args.need_vec(0)?;
+    items.sort_by(compare);
+    Value::from(items).ok()
+}
+
+/// Return a copy of a List sorted by what the function returns for
+/// each item.
+///
+/// `sort-by(['ccc', 'a', 'bb'], len)  #=> ["a", "bb", "ccc"]`
+pub fn sort_by(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    let mut keyed = vec![];
+    for item in args.need_vec(0)? {
+        keyed.push((args.env.call(&f, vec![item.clone()])?, item));
+    }
+    keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+    Value::from(keyed.into_iter().map(|(_, item)| item).collect::<Vec<_>>()).ok()
+}
+
+/// Reverse a List or String.
+///
+/// ```ignore
+/// reverse([1, 2, 3])  #=> [3, 2, 1]
+/// reverse('abc')      #=> "cba"
+/// ```
+pub fn reverse(args: Args) -> Result<Value> {
+    if let Ok(s) = args.need_string(0) {
+        return Value::from(s.chars().rev().collect::<String>()).ok();
+    }
+    let mut items = args.need_vec(0)?;
+    items.reverse();
+    Value::from(items).ok()
+}
+
+/// Return a copy of a List without duplicates, keeping the first of
+/// each.
+///
+/// `uniq([1, 2, 1, 3, 2])  #=> [1, 2, 3]`
+pub fn uniq(args: Args) -> Result<Value> {
+    let mut out: Vec<Value> = vec![];
+    for item in args.need_vec(0)? {
+        if !out.contains(&item) {
+            out.push(item);
+        }
+    }
+    Value::from(out).ok()
+}
+
+/// Group the items of a List into a Map of Lists, keyed by what the
+/// function returns for each item.
+///
+/// `group-by(['ab', 'c', 'de'], len)  #=> { 2: ["ab", "de"], 1: ["c"] }`
+pub fn group_by(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    let mut groups: Vec<(String, Vec<Value>)> = vec![];
+    for item in args.need_vec(0)? {
+        let key = args.env.call(&f, vec![item.clone()])?.to_string();
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((key, vec![item])),
+        }
+    }
+    let mut map = OMap::new();
+    for (key, group) in groups {
+        map.insert(key, group.into());
+    }
+    Value::Map(map.into()).ok()
+}
+
+/// Return the first item in a List the function returns true for, or
+/// None.
+///
+/// `find([1, 2, 3], fn(x) x > 1)  #=> 2`
+pub fn find(args: Args) -> Result<Value> {
+    let f = args.need(1)?;
+    for item in args.need_vec(0)? {
+        if args.env.call(&f, vec![item.clone()])?.to_bool() {
+            return item.ok();
+        }
+    }
+    Value::None.ok()
+}
+
+/// True if any item in a List is true, or if the function returns
+/// true for any item.
+///
+/// ```ignore
+/// any?([false, 1])             #=> true
+/// any?([1, 2], fn(x) x > 5)    #=> false
+/// ```
+pub fn any_(args: Args) -> Result<Value> {
+    let f = args.get(1).cloned();
+    for item in args.need_vec(0)? {
+        let yes = match &f {
+            Some(f) => args.env.call(f, vec![item])?,
+            None => item,
+        };
+        if yes.to_bool() {
+            return Value::Bool(true).ok();
+        }
+    }
+    Value::Bool(false).ok()
+}
+
+/// True if every item in a List is true, or if the function returns
+/// true for every item.
+///
+/// ```ignore
+/// all?([true, 1])              #=> true
+/// all?([1, 2], fn(x) x > 1)    #=> false
+/// ```
+pub fn all_(args: Args) -> Result<Value> {
+    let f = args.get(1).cloned();
+    for item in args.need_vec(0)? {
+        let yes = match &f {
+            Some(f) => args.env.call(f, vec![item])?,
+            None => item,
+        };
+        if !yes.to_bool() {
+            return Value::Bool(false).ok();
+        }
+    }
+    Value::Bool(true).ok()
+}
+
+/// The first item in a List or character in a String, or None if
+/// it's empty.
+///
+/// `first([1, 2, 3])  #=> 1`
+pub fn first(args: Args) -> Result<Value> {
+    if let Ok(s) = args.need_string(0) {
+        return Value::from(s.chars().next().map(String::from)).ok();
+    }
+    Value::from(args.need_vec(0)?.into_iter().next()).ok()
+}
+
+/// The last item in a List or character in a String, or None if it's
+/// empty.
+///
+/// `last([1, 2, 3])  #=> 3`
+pub fn last(args: Args) -> Result<Value> {
+    if let Ok(s) = args.need_string(0) {
+        return Value::from(s.chars().last().map(String::from)).ok();
+    }
+    Value::from(args.need_vec(0)?.pop()).ok()
+}
+
+/// The first `n` items of a List or characters of a String.
+///
+/// `take([1, 2, 3], 2)  #=> [1, 2]`
+pub fn take(args: Args) -> Result<Value> {
+    let n = args.need_number(1)?.max(0.0) as usize;
+    if let Ok(s) = args.need_string(0) {
+        return Value::from(s.chars().take(n).collect::<String>()).ok();
+    }
+    Value::from(args.need_vec(0)?.into_iter().take(n).collect::<Vec<_>>()).ok()
+}
+
+/// Everything but the first `n` items of a List or characters of a
+/// String.
+///
+/// `drop([1, 2, 3], 2)  #=> [3]`
+pub fn drop(args: Args) -> Result<Value> {
+    let n = args.need_number(1)?.max(0.0) as usize;
+    if let Ok(s) = args.need_string(0) {
+        return Value::from(s.chars().skip(n).collect::<String>()).ok();
+    }
+    Value::from(args.need_vec(0)?.into_iter().skip(n).collect::<Vec<_>>()).ok()
+}
+
+/// Pair up the items of two or more Lists. Stops at the end of the
+/// shortest List.
+///
+/// `zip([1, 2, 3], ['a', 'b'])  #=> [[1, "a"], [2, "b"]]`
+pub fn zip(args: Args) -> Result<Value> {
+    let mut lists = vec![];
+    for i in 0..args.len() {
+        lists.push(args.need_vec(i)?);
+    }
+    let len = lists.iter().map(|l| l.len()).min().unwrap_or(0);
+    Value::from(
+        (0..len)
+            .map(|i| Value::from(lists.iter().map(|l| l[i].clone()).collect::<Vec<_>>()))
+            .collect::<Vec<_>>(),
+    )
+    .ok()
+}
+
+/// Pull the items of nested Lists up into one List.
+///
+/// `flatten([1, [2, [3]], 4])  #=> [1, 2, 3, 4]`
+pub fn flatten(args: Args) -> Result<Value> {
+    fn flatten_into(items: &[Value], out: &mut Vec<Value>) {
+        for item in items {
+            match item {
+                Value::List(list) => flatten_into(&list.borrow(), out),
+                _ => out.push(item.clone()),
+            }
+        }
+    }
+    let mut out = vec![];
+    flatten_into(&args.need_vec(0)?, &mut out);
+    Value::from(out).ok()
+}
+
+/// Remove an item from a List by index or a Map by key and return
+/// it, or None if it isn't there. Modifies the List or Map.
+///
+/// ```ignore
+/// a := [1, 2, 3]
+/// remove(a, 0)  #=> 1
+/// a             #=> [2, 3]
+/// ```
+pub fn remove(args: Args) -> Result<Value> {
+    match args.need(0)? {
+        Value::Map(map) => Value::from(map.borrow_mut().remove(args.need_string(1)?)),
+        Value::List(list) => {
+            let len = list.borrow().len() as isize;
+            let mut idx = args.need_number(1)? as isize;
+            if idx < 0 {
+                idx += len;
+            }
+            if idx < 0 || idx >= len {
+                Value::None
+            } else {
+                list.borrow_mut().remove(idx as usize)
+            }
+        }
+        _ => Value::None,
+    }
+    .ok()
+}
+
+//////////////////////////////////////////////////////////////////////
+// Map Functions
+
+/// The keys of a Map, in order.
+///
+/// `keys({ a: 1, b: 2 })  #=> ["a", "b"]`
+pub fn keys(args: Args) -> Result<Value> {
+    let map = args.need_map(0)?;
+    let keys = map
+        .borrow()
+        .iter()
+        .map(|(k, _)| Value::from(k.clone()))
+        .collect::<Vec<_>>();
+    Value::from(keys).ok()
+}
+
+/// The values of a Map, in order.
+///
+/// `values({ a: 1, b: 2 })  #=> [1, 2]`
+pub fn values(args: Args) -> Result<Value> {
+    let map = args.need_map(0)?;
+    let values = map
+        .borrow()
+        .iter()
+        .map(|(_, v)| v.clone())
+        .collect::<Vec<_>>();
+    Value::from(values).ok()
+}
+
+/// Combine Maps into a new Map. Keys in later Maps win.
+///
+/// `merge({ a: 1, b: 2 }, { b: 3 })  #=> { a: 1, b: 3 }`
+pub fn merge(args: Args) -> Result<Value> {
+    let mut out = OMap::new();
+    for i in 0..args.len() {
+        for (k, v) in args.need_map(i)?.borrow().iter() {
+            out.insert(k.clone(), v.clone());
+        }
+    }
+    Value::Map(out.into()).ok()
+}
+
+/// True if a Map or Object has a key.
+///
+/// `has-key?({ a: 1 }, 'a')  #=> true`
+pub fn has_key_(args: Args) -> Result<Value> {
+    let key = args.need_string(1)?;
+    let has = match args.need(0)? {
+        Value::Map(map) => map.borrow().get(key).is_some(),
+        Value::Object(o) => o.get(key).is_some(),
+        _ => false,
+    };
+    Value::Bool(has).ok()
+}
+
 //////////////////////////////////////////////////////////////////////
 // Range Functions
 
@@ -455,10 +1336,9 @@ pub fn range_inclusive(args: Args) -> Result<Value> {
 pub fn print(mut args: Args) -> Result<Value> {
     while !args.is_empty() {
         let arg = args.remove(0);
-        if args.is_empty() {
-            args.env.print(format!("{}", arg));
-        } else {
-            args.env.print(format!("{} ", arg));
+        args.env.print(arg);
+        if !args.is_empty() {
+            args.env.print_raw(" ");
         }
     }
     Value::None.ok()
@@ -468,12 +1348,33 @@ pub fn print(mut args: Args) -> Result<Value> {
 pub fn puts(mut args: Args) -> Result<Value> {
     while !args.is_empty() {
         let arg = args.remove(0);
-        if args.is_empty() {
-            args.env.print(format!("{}", arg));
-        } else {
-            args.env.print(format!("{} ", arg));
+        args.env.print(arg);
+        if !args.is_empty() {
+            args.env.print_raw(" ");
         }
     }
-    args.env.print("\n");
+    args.env.print_raw("\n");
+    Value::None.ok()
+}
+
+/// Render another template in place, using the Env's `Loader`.
+///
+/// `include("partials/nav.hat")`
+pub fn include(args: Args) -> Result<Value> {
+    let name = args.need_string(0)?.to_string();
+    args.env.include(&name)?;
+    Value::None.ok()
+}
+
+/// Bring the functions defined in another template into the current
+/// scope, using the Env's `Loader`.
+///
+/// ```ignore
+/// import("helpers.hat")
+/// <p> format-date(post.date)
+/// ```
+pub fn import(args: Args) -> Result<Value> {
+    let name = args.need_string(0)?.to_string();
+    args.env.import(&name)?;
     Value::None.ok()
 }
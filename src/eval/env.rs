@@ -1,10 +1,20 @@
 use {
-    crate::{builtin, compile, Args, ErrorKind, Fn, OMap, Result, Stmt, Symbol, Tag, Value},
+    crate::{
+        builtin, compile, escape_attr, escape_html,
+        eval::format::{is_void, Formatter},
+        loader::normalize,
+        to_json, Args, Error, ErrorKind, FileLoader, Fn, Format, Literal, Loader, OMap,
+        RenderOptions, Result, Source, Span, Stmt, Symbol, Tag, Template, Value,
+    },
     std::{
         cell::{Ref, RefCell},
         collections::HashMap,
+        fmt,
+        io::{self, Write},
         mem,
         rc::Rc,
+        slice,
+        sync::Arc,
     },
 };
 
@@ -25,7 +35,7 @@ pub fn render(source: &str) -> Result<String> {
 pub enum Jump {
     Break,
     Continue,
-    Return(Value),
+    Return(Box<Value>), // boxed to keep Errors small
 }
 
 /// You know what it is.
@@ -37,6 +47,12 @@ pub type Scope = Rc<RefCell<HashMap<String, Value>>>;
 pub struct Env {
     scopes: Vec<Scope>,
     out: String,
+    blocks: HashMap<String, (Vec<Stmt>, Option<Arc<Source>>)>, // `block`s set by child templates
+    layouts: Vec<String>,                                      // layouts we've extended
+    loader: Box<dyn Loader>,                                   // finds other templates
+    loading: Vec<String>,                                      // templates being included
+    file: Option<Arc<Source>>,                                 // template we're running
+    options: RenderOptions,                                    // pretty, minified, xhtml, etc
 }
 
 impl Default for Env {
@@ -51,6 +67,12 @@ impl Default for Env {
         Env {
             scopes: vec![rcell!(scope)],
             out: String::new(),
+            blocks: HashMap::new(),
+            layouts: vec![],
+            loader: bx!(FileLoader::default()),
+            loading: vec![],
+            file: None,
+            options: RenderOptions::default(),
         }
     }
 }
@@ -61,13 +83,38 @@ impl Env {
         Env::default()
     }
 
+    /// Set the `Loader` used to find other templates for `include()`,
+    /// `import()`, and `extends`. Defaults to a `FileLoader` in the
+    /// current directory.
+    pub fn set_loader<L: Loader + 'static>(&mut self, loader: L) {
+        self.loader = bx!(loader);
+    }
+
+    /// Write XHTML instead of HTML5: void elements like `<br />` are
+    /// self-closed, and attributes like `checked="checked"` always
+    /// have a value. For RSS, Atom, SVG, and other XML.
+    pub fn set_xhtml(&mut self, xhtml: bool) {
+        self.options.xhtml = xhtml;
+    }
+
+    /// How we lay out the HTML we render: as-is, pretty-printed, or
+    /// minified, and whether it's XHTML.
+    pub fn set_options(&mut self, options: RenderOptions) {
+        self.options = options;
+    }
+
+    /// Current rendering options.
+    pub fn options(&self) -> RenderOptions {
+        self.options
+    }
+
     /// Return and clear output.
     pub fn out(&mut self) -> String {
-        mem::replace(&mut self.out, String::new())
+        mem::take(&mut self.out)
     }
 
     /// Create an empty Args struct, to call a helper function.
-    pub fn empty_args(&mut self) -> Args {
+    pub fn empty_args(&mut self) -> Args<'_> {
         Args::new(self, vec![])
     }
 
@@ -109,14 +156,21 @@ impl Env {
             .map(|scope| Ref::map(scope.borrow(), |v| v.get(key).unwrap()))
     }
 
+    /// Is this var a function defined in Rust? Those can be shadowed
+    /// with `:=`, so new builtins don't break existing templates.
+    fn is_builtin(&self, key: &str) -> bool {
+        matches!(
+            self.lookup(key).as_deref(),
+            Some(Value::Fn(Fn::Native(..) | Fn::Special(..)))
+        )
+    }
+
     /// Find the `Scope` in which a var exists, if there is one.
     fn find_scope(&self, key: &str) -> Option<&Scope> {
-        for scope in self.scopes.iter().rev() {
-            if scope.borrow().contains_key(key) {
-                return Some(scope);
-            }
-        }
-        None
+        self.scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.borrow().contains_key(key))
     }
 
     /// Set a value to the nearest scope.
@@ -137,23 +191,288 @@ impl Env {
         .insert(key.to_string(), val.into());
     }
 
+    /// Set a variable for each key in `context`, such as a `HashMap`
+    /// or `Vec<(K, V)>`.
+    pub fn set_context<C: Into<OMap>>(&self, context: C) {
+        for (k, v) in context.into().iter() {
+            self.set(k, v.clone());
+        }
+    }
+
     /// Add something to our render buffer without a trailing line.
+    /// Values are HTML escaped unless they're `Value::Safe`.
     pub fn print<V: Into<Value>>(&mut self, val: V) {
-        self.out.push_str(&val.into().to_string());
+        match val.into() {
+            Value::Safe(s) => self.out.push_str(&s),
+            val => self.out.push_str(&escape_html(&val.to_string())),
+        }
     }
 
-    /// Render statements into a String.
+    /// Add a string to our render buffer as-is, without escaping.
+    pub fn print_raw(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    /// Render source code into a String.
     pub fn render(&mut self, source: &str) -> Result<String> {
-        let stmts = compile(source)?;
-        let autohtml = self.first_is_head(&stmts);
-        if autohtml {
-            self.print("<!DOCTYPE html>\n<html>");
+        let file = Source::new(None, source);
+        let stmts = compile(source).map_err(|e| e.in_source(&file))?;
+        self.render_file(Some(file), &stmts)
+    }
+
+    /// Render source code, writing HTML to `w` as it's produced.
+    /// Tags and `block`s are written as their bodies render, but a
+    /// `for`, `if`, or other code outside of a tag is only written
+    /// once it finishes.
+    pub fn render_to<W: Write>(&mut self, source: &str, w: &mut W) -> Result<()> {
+        let file = Source::new(None, source);
+        let stmts = compile(source).map_err(|e| e.in_source(&file))?;
+        self.render_file_to(Some(file), &stmts, w)
+    }
+
+    /// Like `render_to()`, but for a `fmt::Write` like a `String`
+    /// or a `fmt::Formatter`.
+    pub fn render_to_fmt<W: fmt::Write>(&mut self, source: &str, w: &mut W) -> Result<()> {
+        let file = Source::new(None, source);
+        let stmts = compile(source).map_err(|e| e.in_source(&file))?;
+        self.render_file_to_fmt(Some(file), &stmts, w)
+    }
+
+    /// Render statements into a String. If they `extends` a layout,
+    /// the layout is rendered using their `block`s.
+    pub fn render_stmts(&mut self, stmts: &[Stmt]) -> Result<String> {
+        self.render_file(None, stmts)
+    }
+
+    /// Render statements, writing HTML to `w` as it's produced.
+    pub fn render_stmts_to<W: Write>(&mut self, stmts: &[Stmt], w: &mut W) -> Result<()> {
+        self.render_file_to(None, stmts, w)
+    }
+
+    /// Render statements compiled from `file` into a String.
+    pub(crate) fn render_file(
+        &mut self,
+        file: Option<Arc<Source>>,
+        stmts: &[Stmt],
+    ) -> Result<String> {
+        let mut out = vec![];
+        self.render_file_to(file, stmts, &mut out)?;
+        String::from_utf8(out).map_err(|e| error_kind!(RuntimeError, "{}", e))
+    }
+
+    /// Render statements compiled from `file`, writing HTML to `w`.
+    /// Errors point into `file`, or whichever template they came from.
+    pub(crate) fn render_file_to(
+        &mut self,
+        file: Option<Arc<Source>>,
+        stmts: &[Stmt],
+        w: &mut dyn Write,
+    ) -> Result<()> {
+        let out = match self.options.format {
+            Format::Default => self.render_layout(file.as_ref(), stmts, w),
+            format => {
+                let mut fmt = Formatter::new(format, w);
+                self.render_layout(file.as_ref(), stmts, &mut fmt)
+                    .and_then(|_| Ok(fmt.finish()?))
+            }
+        };
+        // a failed render can leave output behind
+        self.out.clear();
+        self.file = None;
+        self.blocks.clear();
+        self.layouts.clear();
+        out
+    }
+
+    /// Render statements compiled from `file`, writing HTML to a
+    /// `fmt::Write`.
+    pub(crate) fn render_file_to_fmt(
+        &mut self,
+        file: Option<Arc<Source>>,
+        stmts: &[Stmt],
+        w: &mut dyn fmt::Write,
+    ) -> Result<()> {
+        let mut w = FmtWriter { w, partial: vec![] };
+        self.render_file_to(file, stmts, &mut w)
+    }
+
+    /// Follow `extends` to the outermost layout, then render it.
+    fn render_layout(
+        &mut self,
+        file: Option<&Arc<Source>>,
+        stmts: &[Stmt],
+        w: &mut dyn Write,
+    ) -> Result<()> {
+        self.file = file.cloned();
+        match self.extends(file, stmts) {
+            Ok(Some(layout)) => {
+                return self.render_layout(Some(&layout.file()), layout.stmts()?, w);
+            }
+            Ok(None) => {}
+            Err(e) => return Err(in_source(e, file)),
         }
-        self.printed_block(&stmts)?;
+        let autohtml = self.first_is_head(stmts);
         if autohtml {
-            self.print("\n</html>\n");
+            self.print_raw("<!DOCTYPE html>\n<html>");
+        }
+        let out = self.stream_block(stmts, w).and_then(|_| {
+            if autohtml {
+                self.print_raw("\n</html>\n");
+            }
+            self.flush_to(w)
+        });
+        out.map_err(|e| in_source(e, file))
+    }
+
+    /// Write everything rendered so far to `w`.
+    fn flush_to(&mut self, w: &mut dyn Write) -> Result<()> {
+        w.write_all(self.out.as_bytes())?;
+        self.out.clear();
+        Ok(())
+    }
+
+    /// Like `printed_block`, but writes to `w` after each statement
+    /// instead of keeping everything in memory. Tags and `block`s
+    /// are streamed as their bodies are rendered. Anything else, like
+    /// a `for` or `if`, is run with `printed_block` and written once
+    /// it's done.
+    fn stream_block(&mut self, stmts: &[Stmt], w: &mut dyn Write) -> Result<()> {
+        for stmt in stmts {
+            match stmt {
+                Stmt::Tag(tag) => self.print_tag(tag, Some(w))?,
+                Stmt::Block(name, body) => {
+                    self.push_scope();
+                    let out = if let Some((body, file)) = self.blocks.get(name.to_str()).cloned() {
+                        self.in_file(file.clone(), |env| env.stream_block(&body, w))
+                            .map_err(|e| in_source(e, file.as_ref()))
+                    } else {
+                        self.stream_block(body, w)
+                    };
+                    self.pop_scope();
+                    out?;
+                }
+                _ => {
+                    self.printed_block(slice::from_ref(stmt))?;
+                }
+            }
+            self.flush_to(w)?;
+        }
+        Ok(())
+    }
+
+    /// If `stmts` extends a layout, save its `block`s and run its
+    /// other code without printing anything, then return the layout.
+    fn extends(
+        &mut self,
+        file: Option<&Arc<Source>>,
+        stmts: &[Stmt],
+    ) -> Result<Option<Arc<Template>>> {
+        let layout = match stmts.iter().find_map(|stmt| match stmt {
+            Stmt::Extends(layout) => Some(layout),
+            _ => None,
+        }) {
+            Some(layout) => layout,
+            None => return Ok(None),
+        };
+
+        let old_out = self.out();
+        for stmt in stmts {
+            match stmt {
+                Stmt::Extends(..) | Stmt::Tag(..) | Stmt::Word(..) | Stmt::Value(..) => {}
+                // child templates override the blocks of their layouts
+                Stmt::Block(name, body) => {
+                    self.blocks
+                        .entry(name.to_string())
+                        .or_insert_with(|| (body.clone(), file.cloned()));
+                }
+                _ => {
+                    self.eval(stmt)?;
+                }
+            }
+        }
+        self.out = old_out;
+
+        let name = self.eval(layout)?.to_string();
+        if self
+            .layouts
+            .iter()
+            .any(|n| normalize(n) == normalize(&name))
+        {
+            return error!("layout {} extends itself", name);
         }
-        Ok(self.out())
+        let layout = self.loader.load_template(&name)?;
+        self.layouts.push(name);
+        Ok(Some(layout))
+    }
+
+    /// Load the source code of another template using our `Loader`.
+    pub fn load(&self, name: &str) -> Result<String> {
+        self.loader.load(name)
+    }
+
+    /// Render another template in place.
+    pub fn include(&mut self, name: &str) -> Result<()> {
+        let template = self.start_loading(name, "include")?;
+        let (file, stmts) = (template.file(), template.stmts()?);
+        self.push_scope();
+        let out = self.in_file(Some(file.clone()), |env| env.printed_block(stmts));
+        self.pop_scope();
+        self.loading.pop();
+        out.map(|_| ())
+            .map_err(|e| e.in_source(&file).included("include"))
+    }
+
+    /// Run another template in its own top-level scope, where only
+    /// Rust functions are defined, and bring the Hatter functions it
+    /// defines into the current scope. Nothing it prints is kept.
+    pub fn import(&mut self, name: &str) -> Result<()> {
+        let template = self.start_loading(name, "import")?;
+        let (file, stmts) = (template.file(), template.stmts()?);
+        let old_out = self.out();
+        let natives = self.scopes[0]
+            .borrow()
+            .iter()
+            .filter(|(_, v)| matches!(v, Value::Fn(Fn::Native(..) | Fn::Special(..))))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<HashMap<_, _>>();
+        let old_scopes = mem::replace(&mut self.scopes, vec![rcell!(natives)]);
+        let out = self.in_file(Some(file.clone()), |env| env.block(stmts));
+        let fns = self
+            .scope()
+            .borrow()
+            .iter()
+            .filter(|(_, v)| matches!(v, Value::Fn(Fn::Fn(..))))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>();
+        self.scopes = old_scopes;
+        self.loading.pop();
+        self.out = old_out;
+        out.map_err(|e| e.in_source(&file).included("import"))?;
+        for (name, f) in fns {
+            self.set(&name, f);
+        }
+        Ok(())
+    }
+
+    /// Load and compile a template we're about to `include()` or
+    /// `import()`, checking for cycles.
+    fn start_loading(&mut self, name: &str, how: &str) -> Result<Arc<Template>> {
+        if self.loading.iter().any(|n| normalize(n) == normalize(name)) {
+            return error!("include cycle: {} -> {}", self.loading.join(" -> "), name);
+        }
+        let template = self.loader.load_template(name)?;
+        template.compile().map_err(|e| e.included(how))?;
+        self.loading.push(name.to_string());
+        Ok(template)
+    }
+
+    /// Run `f` as part of the template `file`, so functions it
+    /// defines know where they came from.
+    fn in_file<T>(&mut self, file: Option<Arc<Source>>, f: impl FnOnce(&mut Env) -> T) -> T {
+        let old = mem::replace(&mut self.file, file);
+        let out = f(self);
+        self.file = old;
+        out
     }
 
     /// Evaluate a block of statements, returning the last one.
@@ -161,7 +480,7 @@ impl Env {
         let mut out = Value::None;
         for stmt in stmts {
             let is_tag = matches!(stmt, Stmt::Tag(..));
-            out = self.eval(&stmt)?;
+            out = self.eval(stmt)?;
             if is_tag {
                 self.print(&out);
             }
@@ -169,19 +488,150 @@ impl Env {
         Ok(out)
     }
 
+    /// Run `f`. If it fails, throw away whatever it printed and any
+    /// scopes it left behind before returning the error. `break`,
+    /// `continue`, and `return` aren't failures and keep their output.
+    pub fn attempt<F>(&mut self, f: F) -> Result<Value>
+    where
+        F: FnOnce(&mut Env) -> Result<Value>,
+    {
+        let depth = self.scopes.len();
+        let start = self.out.len();
+        let out = f(self);
+        if let Err(e) = &out {
+            if !matches!(e.kind, ErrorKind::Jump(..)) {
+                self.scopes.truncate(depth);
+                self.out.truncate(start);
+            }
+        }
+        out
+    }
+
+    /// Call any function with args that have already been evaluated,
+    /// like a builtin calling the function it was given or Rust code
+    /// calling a helper defined in a template.
+    pub fn call(&mut self, f: &Value, args: Vec<Value>) -> Result<Value> {
+        self.call_from(f, args, None)
+    }
+
+    /// Look up a function by name and `call()` it.
+    pub fn call_named(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let f = match self.lookup(name) {
+            Some(f) => f.clone(),
+            None => return error!("can't find fn: {}", name),
+        };
+        self.call(&f, args)
+    }
+
+    /// Like `call()`, but errors raised inside a Hatter function are
+    /// tagged with the name and position of the call `site`.
+    fn call_from(
+        &mut self,
+        f: &Value,
+        args: Vec<Value>,
+        site: Option<(&str, Span)>,
+    ) -> Result<Value> {
+        let depth = self.scopes.len();
+        match f {
+            Value::Fn(Fn::Native(f)) => f.clone()(Args::new(self, args)),
+            Value::Fn(Fn::Special(f)) => {
+                // specials take syntax, so pass each arg as a variable
+                // that can't be named in Hatter code
+                self.push_scope();
+                let args = args
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, val)| {
+                        let name = format!("(arg {})", i);
+                        self.set(&name, val);
+                        Stmt::Word(name.into(), Span::default())
+                    })
+                    .collect::<Vec<_>>();
+                let out = f.clone()(self, &args);
+                self.scopes.truncate(depth);
+                out
+            }
+            Value::Fn(Fn::Fn(params, body, scope, file)) => {
+                if params.len() != args.len() {
+                    return error!("expected {} args, got {}", params.len(), args.len());
+                }
+                self.scopes.push(scope.clone());
+                self.push_scope();
+                for (name, val) in params.iter().zip(args) {
+                    self.set(name, val);
+                }
+                let out = self.in_file(file.clone(), |env| env.block(body));
+                // drop the closure scope, args, and anything a `return` skipped
+                self.scopes.truncate(depth);
+                out.or_else(|e| match e.kind {
+                    ErrorKind::Jump(Jump::Return(v)) => Ok(*v),
+                    ErrorKind::Jump(..) => Err(e),
+                    // the error is in the fn's template, not the caller's
+                    _ => {
+                        let e = in_source(e, file.as_ref());
+                        match site {
+                            Some((name, span)) => Err(e.called_from(name, span)),
+                            None => Err(e),
+                        }
+                    }
+                })
+            }
+            _ => error!("can't call {}", f.typename()),
+        }
+    }
+
+    /// Evaluate the args to a Hatter function in the caller's scope.
+    /// Keyword args are put in the same order as `params`.
+    fn eval_args(&mut self, params: &[Symbol], args: &[Stmt]) -> Result<Vec<Value>> {
+        if let [Stmt::Args(kw_args)] = args {
+            return params
+                .iter()
+                .filter_map(|name| kw_args.iter().find(|(kw, _)| kw == name))
+                .map(|(_, arg)| self.eval(arg))
+                .collect();
+        }
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+
     /// Evaluate a block of statements and print what each returns.
     /// "Outside of a tag, tags are printed.
     ///  Inside of a tag, everything is printed."
+    /// Text and string literals from the template are printed as-is,
+    /// everything else is HTML escaped.
     pub fn printed_block(&mut self, stmts: &[Stmt]) -> Result<Value> {
         let mut out = Value::None;
         for stmt in stmts {
-            if let Stmt::Word(w) = &stmt {
-                if !self.is_keyword_or_var(w) {
-                    self.print(w);
+            match stmt {
+                Stmt::Word(w, _) if !self.is_keyword_or_var(w) => {
+                    self.print_raw(w.to_str());
+                    continue;
+                }
+                Stmt::Value(Literal::String(s)) => {
+                    self.print_raw(s.to_str());
+                    out = Value::String(s.clone());
+                    continue;
+                }
+                // "Interpolated {strings}" only escape what's interpolated.
+                Stmt::Call(target, parts, _) if target.to_str() == "concat" => {
+                    for part in parts {
+                        if let Stmt::Value(Literal::String(s)) = part {
+                            self.print_raw(s.to_str());
+                        } else {
+                            let val = self.eval(part)?;
+                            self.print(val);
+                        }
+                    }
+                    out = Value::None;
                     continue;
                 }
+                Stmt::Tag(tag) => {
+                    self.print_tag(tag, None)?;
+                    out = Value::None;
+                    continue;
+                }
+                _ => {}
             }
-            out = self.eval(&stmt)?;
+            out = self.eval(stmt)?;
             if !matches!(out, Value::None) {
                 self.print(&out);
             }
@@ -194,15 +644,33 @@ impl Env {
         compile(src).and_then(|c| self.block(&c))
     }
 
-    /// Evaluate one statement, returning its Value.
+    /// Evaluate one statement, returning its Value. Errors point at
+    /// the innermost Stmt we know the position of.
     pub fn eval(&mut self, stmt: &Stmt) -> Result<Value> {
+        self.eval_stmt(stmt).map_err(|e| match stmt.span() {
+            Some(span) => e.at(span),
+            None => e,
+        })
+    }
+
+    fn eval_stmt(&mut self, stmt: &Stmt) -> Result<Value> {
         Ok(match stmt {
             Stmt::None => Value::None,
-            Stmt::Value(v) => v.clone(),
-            Stmt::Tag(x) => self.eval_tag(x)?,
+            Stmt::Value(v) => v.to_value(),
+            Stmt::Tag(tag) => {
+                let start = self.out.len();
+                let printed = self.print_tag(tag, None);
+                let html = self.out.split_off(start);
+                printed?;
+                if html.is_empty() {
+                    Value::None
+                } else {
+                    Value::Safe(html.into())
+                }
+            }
             Stmt::List(x) => x
                 .iter()
-                .map(|v| self.eval(&v))
+                .map(|v| self.eval(v))
                 .collect::<Result<Vec<_>>>()?
                 .into(),
             Stmt::Map(pairs) => {
@@ -212,84 +680,51 @@ impl Env {
                 }
                 Value::Map(map.into())
             }
-            Stmt::Word(word) => {
+            Stmt::Word(word, _) => {
                 match word.as_ref() {
                     "break" => return jump!(Jump::Break),
                     "continue" => return jump!(Jump::Continue),
                     _ => {}
                 }
-                if let Some(val) = self.lookup(&word) {
+                if let Some(val) = self.lookup(word) {
                     val.clone()
                 } else {
                     return error!("can't find var: {}", word);
                 }
             }
-            Stmt::Call(target, args) => {
-                // eval the target and see if it's a Hatter function
-                if let Value::Fn(inner_fn) = self.eval(&target)? {
-                    match inner_fn {
-                        Fn::Special(f) => f.clone()(self, args)?,
-                        Fn::Native(f) => {
-                            let f = f.clone();
+            Stmt::Call(target, args, span) => {
+                // obj.method(args) calls the Object's method
+                let fun = match &**target {
+                    Stmt::Call(dot, parts, _) if dot.to_str() == "." && parts.len() == 2 => {
+                        let subject = self.eval(&parts[0])?;
+                        let key = self.eval(&parts[1])?;
+                        if let Value::Object(o) = subject {
                             let args = args
                                 .iter()
-                                .map(|a| self.eval(&a))
+                                .map(|a| self.eval(a))
                                 .collect::<Result<Vec<_>>>()?;
-                            f(Args::new(self, args))?
-                        }
-                        Fn::Fn(params, body, scope) => {
-                            let mut kw_args = None;
-                            if args.len() == 1 {
-                                if let Stmt::Args(inner) = &args[0] {
-                                    let mut args = vec![];
-                                    for (i, name) in params.iter().enumerate() {
-                                        if let Some(pair) = inner.iter().find(|(kw, _)| kw == name)
-                                        {
-                                            args.push((i, pair.1.clone()));
-                                        }
-                                    }
-                                    kw_args = Some(args);
-                                }
-                            }
-                            let kw_args = if let Some(mut inner) = kw_args {
-                                inner.sort_by(|a, b| a.0.cmp(&b.0));
-                                inner.into_iter().map(|p| p.1).collect::<Vec<_>>()
-                            } else {
-                                vec![]
-                            };
-                            let args = if !kw_args.is_empty() { &kw_args } else { args };
-
-                            if params.len() != args.len() {
-                                return error!(
-                                    "expected {} args, got {}",
-                                    params.len(),
-                                    args.len()
-                                );
-                            }
-                            self.scopes.push(scope);
-                            self.push_scope();
-                            for (i, a) in args.iter().enumerate() {
-                                if let Some(name) = params.get(i) {
-                                    let val = self.eval(&a)?;
-                                    self.set(name, val);
-                                }
-                            }
-                            let out = self.block(&body);
-                            self.pop_scope();
-                            match out {
-                                Ok(v) => v,
-                                Err(e) => match e.kind {
-                                    ErrorKind::Jump(Jump::Return(v)) => v,
-                                    _ => return Err(e),
-                                },
-                            }
+                            return o.call(key.to_str(), Args::new(self, args));
                         }
+                        builtin::index(Args::new(self, vec![subject, key]))?
                     }
-                } else {
-                    return error!("can't find fn: {}", target.to_string());
-                }
+                    _ => self.eval(target)?,
+                };
+
+                let args = match &fun {
+                    Value::Fn(Fn::Special(f)) => return f.clone()(self, args),
+                    Value::Fn(Fn::Native(..)) => {
+                        args.iter().map(|a| self.eval(a)).collect::<Result<_>>()?
+                    }
+                    Value::Fn(Fn::Fn(params, ..)) => self.eval_args(params, args)?,
+                    _ => return error!("can't find fn: {}", target.to_string()),
+                };
+                let name = match target.to_str() {
+                    "" => "fn",
+                    name => name,
+                };
+                self.call_from(&fun, args, Some((name, *span)))?
             }
-            Stmt::Return(expr) => return jump!(Jump::Return(self.eval(expr)?)),
+            Stmt::Return(expr) => return jump!(Jump::Return(Box::new(self.eval(expr)?))),
             Stmt::If(conds) => {
                 for (test, body) in conds {
                     if self.eval(test)?.to_bool() {
@@ -304,8 +739,8 @@ impl Env {
             Stmt::For(..) => self.eval_for(stmt)?,
             Stmt::While(test, body) => {
                 self.push_scope();
-                while self.eval(&test)?.to_bool() {
-                    match self.block(&body) {
+                while self.eval(test)?.to_bool() {
+                    match self.block(body) {
                         Ok(_) => {}
                         Err(e) => match e.kind {
                             ErrorKind::Jump(Jump::Break) => break,
@@ -318,9 +753,9 @@ impl Env {
                 self.pop_scope();
                 Value::None
             }
-            Stmt::Assign(name, expr, is_reassign) => {
+            Stmt::Assign(name, expr, is_reassign, _) => {
                 let exists = self.var_exists(name);
-                if exists && !is_reassign {
+                if exists && !is_reassign && !self.is_builtin(name) {
                     return error!("{} already set", name);
                 } else if !exists && *is_reassign {
                     return error!("{} is not set", name);
@@ -337,121 +772,311 @@ impl Env {
                 }
                 Value::None
             }
-            Stmt::Fn(params, body) => {
-                Value::Fn(Fn::Fn(params.clone(), body.clone(), self.scope().clone()))
+            Stmt::Fn(params, body) => Value::Fn(Fn::Fn(
+                params.clone(),
+                body.clone(),
+                self.scope().clone(),
+                self.file.clone(),
+            )),
+            // keyword args to Rust functions and Object methods are
+            // passed as one Map, like the attributes of a <Component>
+            Stmt::Args(kw_args) => {
+                let mut map = OMap::new();
+                for (name, arg) in kw_args {
+                    let val = self.eval(arg)?;
+                    map.insert(name.to_str(), val);
+                }
+                Value::Map(map.into())
+            }
+            Stmt::Block(name, body) => {
+                self.push_scope();
+                let out = if let Some((body, file)) = self.blocks.get(name.to_str()).cloned() {
+                    self.in_file(file.clone(), |env| env.printed_block(&body))
+                        .map_err(|e| in_source(e, file.as_ref()))
+                } else {
+                    self.printed_block(body)
+                };
+                self.pop_scope();
+                out?;
+                Value::None
+            }
+            Stmt::Extends(..) => return error!("extends must be at the top level"),
+            Stmt::Try(body, var, rescue) => {
+                self.push_scope();
+                let out = self.attempt(|env| env.block(body));
+                self.pop_scope();
+                match out {
+                    Err(e) if !matches!(e.kind, ErrorKind::Jump(..)) => {
+                        self.push_scope();
+                        if let Some(var) = var {
+                            self.set(var, e.details);
+                        }
+                        let out = self.block(rescue);
+                        self.pop_scope();
+                        out?;
+                    }
+                    out => {
+                        out?;
+                    }
+                }
+                Value::None
             }
-            Stmt::Args(..) => unimplemented!(),
         })
     }
 
-    /// Turn a <tag> into a Value::String.
-    fn eval_tag(&mut self, tag: &Tag) -> Result<Value> {
-        let mut out = String::new();
-        out.push('<');
-
-        // tag name
+    /// Print a <tag> straight into our output. If we're streaming to
+    /// `w`, its body is written out as it's rendered.
+    fn print_tag(&mut self, tag: &Tag, w: Option<&mut dyn Write>) -> Result<()> {
         let tagname = self.eval(&tag.tag)?.to_string();
-        out.push_str(&tagname);
-        out.push(' ');
 
         // don't run <debug> code in --release
         #[cfg(not(debug_assertions))]
         if tagname == "debug" {
-            return Ok(Value::None);
+            return Ok(());
         }
 
-        // id
-        if tag.id.is_some() {
-            let val = self.eval(&tag.id)?;
-            if val.to_bool() {
-                out.push_str(&format!("id='{}'", val.to_string()));
-                out.push(' ');
+        // <Card> calls the function Card, if there is one. Otherwise
+        // it's a regular tag, so <HTML> and <DIV> still work.
+        if tagname.starts_with(|c: char| c.is_uppercase()) {
+            let component = match self.lookup(&tagname).as_deref() {
+                Some(f @ Value::Fn(..)) => Some(f.clone()),
+                _ => None,
+            };
+            if let Some(f) = component {
+                return self.print_component(f, &tagname, tag);
+            }
+        }
+
+        let start = self.out.len();
+        self.open_tag(&tagname, tag)?;
+        let void = is_void(&tagname);
+        if tag.closed || (void && tag.body.is_empty()) {
+            return Ok(());
+        }
+
+        // body. void elements can't have one, so `<input> Label` puts
+        // the text after the <input>.
+        match w {
+            Some(w) if tagname != "debug" => {
+                self.flush_to(w)?;
+                self.stream_block(&tag.body, w)?;
+            }
+            _ => {
+                self.printed_block(&tag.body)?;
+            }
+        }
+        if void {
+            return Ok(());
+        }
+
+        // closing tag
+        self.print_raw("</");
+        self.print_raw(&tagname);
+        self.print_raw(">\n");
+
+        // <debug> gets eval'd but not print'd in --debug mode
+        if tagname == "debug" {
+            self.out.truncate(start);
+        }
+        Ok(())
+    }
+
+    /// Render a `<Component>` by calling `f`, the function of the
+    /// same name. Its attributes are passed as keyword args, its rendered
+    /// body as `children`, and each `<slot name=x>` in its body as `x`.
+    /// Params with no matching attribute get None, and an `attrs`
+    /// param gets a Map of the attributes no other param took.
+    /// Functions defined in Rust get all of this as one Map.
+    fn print_component(&mut self, f: Value, name: &str, tag: &Tag) -> Result<()> {
+        let mut props = self.tag_attrs(tag)?;
+
+        // the body renders in the caller's scope, before the call
+        let mut children = vec![];
+        for stmt in &tag.body {
+            match stmt {
+                Stmt::Tag(slot) if slot.tag.to_str() == "slot" => {
+                    let slot_name = match slot.attrs.iter().find(|(k, _)| k.to_str() == "name") {
+                        Some((_, val)) => self.eval(val)?.to_string(),
+                        None => return error!("<slot> in <{}> needs a name", name),
+                    };
+                    let html = self.capture(&slot.body)?;
+                    props.insert(slot_name, html);
+                }
+                _ => children.push(stmt.clone()),
             }
         }
+        let children = self.capture(&children)?;
+        if !matches!(children, Value::None) {
+            props.insert("children", children);
+        }
 
-        // classes
-        if !tag.classes.is_empty() {
-            let mut classes = vec![];
-            for class in &tag.classes {
-                let val = self.eval(class)?;
-                if val.to_bool() {
-                    classes.push(val.to_string());
+        let args = match &f {
+            Value::Fn(Fn::Fn(params, ..)) => {
+                let mut args = params
+                    .iter()
+                    .map(|p| props.remove(p).unwrap_or(Value::None))
+                    .collect::<Vec<_>>();
+                if let Some(i) = params.iter().position(|p| p == "attrs") {
+                    args[i] = Value::Map(mem::replace(&mut props, OMap::new()).into());
+                }
+                if let Some((key, _)) = props.iter().next() {
+                    return error!("<{}> doesn't take {}", name, key);
                 }
+                args
             }
-            out.push_str(&format!("class='{}'", classes.join(" ")));
-            out.push(' ');
+            _ => vec![Value::Map(props.into())],
+        };
+
+        // print what the function printed, or else what it returned
+        let start = self.out.len();
+        let val = self.call(&f, args)?;
+        if self.out.len() == start {
+            self.print(&val);
         }
+        Ok(())
+    }
 
-        // attributes
+    /// Evaluate a tag's id, classes, and attributes, in order, with
+    /// `...spread` Maps mixed in. Later attributes replace earlier
+    /// ones, except `class`, which is merged into one safe String.
+    fn tag_attrs(&mut self, tag: &Tag) -> Result<OMap> {
+        let mut attrs = OMap::new();
+        let mut classes = vec![];
+        if tag.id.is_some() {
+            let id = self.eval(&tag.id)?;
+            if id.to_bool() {
+                attrs.insert("id", id);
+            }
+        }
+        for class in &tag.classes {
+            let val = self.eval(class)?;
+            add_attr(&mut attrs, &mut classes, "class", val)?;
+        }
+        for (name, val) in &tag.attrs {
+            let val = self.eval(val)?;
+            if name.is_some() {
+                let name = self.eval(name)?.to_string();
+                add_attr(&mut attrs, &mut classes, &name, val)?;
+                continue;
+            }
+            match val {
+                Value::Map(map) => {
+                    for (name, val) in map.borrow().iter() {
+                        add_attr(&mut attrs, &mut classes, name, val.clone())?;
+                    }
+                }
+                // <input {flag}> where flag is an attribute name
+                Value::String(..) | Value::Safe(..) => {
+                    add_attr(&mut attrs, &mut classes, &val.to_string(), true.into())?
+                }
+                Value::None | Value::Bool(false) => {}
+                val => return error!("can't spread {} into attributes", val.typename()),
+            }
+        }
+        if classes.is_empty() {
+            attrs.remove("class");
+        } else {
+            attrs.insert("class", Value::Safe(classes.join(" ").into()));
+        }
+        Ok(attrs)
+    }
+
+    /// Run statements in a new scope and return what they print as a
+    /// safe String, or None if they print nothing.
+    fn capture(&mut self, stmts: &[Stmt]) -> Result<Value> {
+        let start = self.out.len();
+        self.push_scope();
+        let out = self.printed_block(stmts);
+        self.pop_scope();
+        let html = self.out.split_off(start);
+        out?;
+        Ok(if html.is_empty() {
+            Value::None
+        } else {
+            Value::Safe(html.into())
+        })
+    }
+
+    /// Print the opening of a <tag>, with its attributes.
+    fn open_tag(&mut self, tagname: &str, tag: &Tag) -> Result<()> {
+        let mut out = String::new();
+        out.push('<');
+        out.push_str(tagname);
+        out.push(' ');
+
+        let attrs = self.tag_attrs(tag)?;
         let is_form = tagname == "form";
         let is_link = tagname == "a";
         let mut has_href = !is_link;
-        for (name, val) in &tag.attrs {
-            let val = self.eval(val)?;
+        for (attr_name, val) in attrs.iter() {
             if !val.to_bool() {
                 continue;
             }
-
-            let attr_name = self.eval(name)?.to_string();
-            if is_form && matches!(attr_name.as_ref(), "GET" | "POST") {
-                out.push_str(&format!("method='{}' action='{}'", attr_name, val));
+            if is_form && matches!(attr_name.to_str(), "GET" | "POST") {
+                out.push_str(&format!(
+                    "method=\"{}\" action=\"{}\"",
+                    attr_name,
+                    attr_value(val)
+                ));
             } else {
                 if attr_name == "href" {
                     has_href = true;
                 }
                 match val {
-                    Value::String(s) => out.push_str(&format!("{}='{}'", attr_name, s)),
-                    Value::Bool(..) => out.push_str(&attr_name),
-                    _ => out.push_str(&format!("{}={}", attr_name, val)),
+                    // XHTML has no attributes without values
+                    Value::Bool(..) if self.options.xhtml => {
+                        out.push_str(&format!("{}=\"{}\"", attr_name, attr_name))
+                    }
+                    Value::Bool(..) => out.push_str(attr_name),
+                    Value::List(..) | Value::Map(..) => out.push_str(&format!(
+                        "{}=\"{}\"",
+                        attr_name,
+                        escape_attr(&to_json(val)?)
+                    )),
+                    _ => out.push_str(&format!("{}=\"{}\"", attr_name, attr_value(val))),
                 }
-                out.push(' ');
             }
+            out.push(' ');
         }
 
         if is_link && !has_href {
-            out.push_str(&"href='#' ");
-        }
-
-        // check for self-closing tag
-        if tag.closed {
-            out.push('/');
-            out.push('>');
-            out.push('\n');
-            return Ok(out.into());
+            out.push_str("href=\"#\" ");
         }
 
         // trim trailing space
         if matches!(out.bytes().last(), Some(b' ')) {
             out.truncate(out.len() - 1);
         }
-
-        // close tag
-        out.push('>');
-
-        // body
-        let old_out = self.out();
-        self.printed_block(&tag.body)?;
-        out.push_str(&mem::replace(&mut self.out, old_out));
-
-        // closing tag
-        out.push_str(&format!("</{}>\n", tagname));
-
-        // <debug> gets eval'd but not print'd in --debug mode
-        if tagname == "debug" {
-            Ok(Value::None)
+        if is_void(tagname) {
+            out.push_str(if self.options.xhtml { " />\n" } else { ">\n" });
+        } else if tag.closed && self.options.xhtml {
+            out.push_str(" />\n");
+        } else if tag.closed {
+            // HTML5 ignores the / on non-void tags, so close it for real
+            out.push_str(&format!("></{}>\n", tagname));
         } else {
-            Ok(out.into())
+            out.push('>');
         }
+
+        self.print_raw(&out);
+        Ok(())
     }
 
     /// Evaluate a for loop.
     fn eval_for(&mut self, stmt: &Stmt) -> Result<Value> {
         if let Stmt::For(key, val, expr, body) = stmt {
-            match self.eval(&expr)? {
+            match self.eval(expr)? {
                 Value::List(list) => {
                     self.inner_for(key, val, list.borrow().iter().enumerate(), body)?
                 }
                 Value::Map(map) => self.inner_for(key, val, map.borrow().iter(), body)?,
+                Value::Object(o) => match o.iter() {
+                    Some(iter) => {
+                        let items = iter.collect::<Vec<_>>();
+                        self.inner_for(key, val, items.iter().enumerate(), body)?
+                    }
+                    None => return error!("can't iterate over {}", o.typename()),
+                },
                 v => return error!("expected List or Map, got {:?}", v),
             }
         }
@@ -476,7 +1101,7 @@ impl Env {
                 self.set(keyvar, k);
             }
             self.set(var, v.clone());
-            match self.block(&body) {
+            match self.block(body) {
                 Ok(_) => {}
                 Err(e) => match e.kind {
                     ErrorKind::Jump(Jump::Break) => break,
@@ -492,16 +1117,135 @@ impl Env {
 
     /// Is the first stmt a <head> tag?
     fn first_is_head(&self, stmts: &[Stmt]) -> bool {
-        if let Some(Stmt::Tag(tag)) = stmts.get(0) {
-            if let Stmt::Value(Value::String(tag)) = &*tag.tag {
+        if let Some(Stmt::Tag(tag)) = stmts.first() {
+            if let Stmt::Value(Literal::String(tag)) = &*tag.tag {
                 return tag == "head";
             }
         }
         false
     }
 
-    /// Is the given string a keyword or existing variable?
+    /// Is the given string a keyword or existing variable? Rust
+    /// functions don't count unless shadowed, so adding a builtin
+    /// doesn't turn words like `map` in text into `{function}`.
     fn is_keyword_or_var(&self, it: &str) -> bool {
-        self.var_exists(it) || matches!(it, "return" | "break" | "continue")
+        (self.var_exists(it) && !self.is_builtin(it))
+            || matches!(it, "return" | "break" | "continue")
+    }
+}
+
+/// Errors leaving a template know they came from it.
+fn in_source(e: Error, file: Option<&Arc<Source>>) -> Error {
+    match file {
+        Some(file) => e.in_source(file),
+        None => e,
+    }
+}
+
+/// Set an attribute, or add to the list of classes. The first
+/// class holds `class`'s place in the attribute order. `style` can be
+/// a Map of CSS properties, and `data` and `aria` can be Maps that
+/// become `data-*` and `aria-*` attributes.
+fn add_attr(attrs: &mut OMap, classes: &mut Vec<String>, name: &str, val: Value) -> Result<()> {
+    match (name, val) {
+        ("class", val) => {
+            if attrs.get("class").is_none() {
+                attrs.insert("class", Value::None);
+            }
+            add_class(classes, &val);
+        }
+        ("style", Value::Map(map)) => {
+            let mut style = vec![];
+            for (k, v) in map.borrow().iter() {
+                // CSS property names, like `font-size` or `--gap`
+                if k.is_empty() || !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                    return error!("bad style property: {:?}", k);
+                }
+                if v.to_bool() {
+                    style.push(format!("{}: {}", k, v));
+                }
+            }
+            attrs.insert("style", style.join("; ").into());
+        }
+        (prefix @ ("data" | "aria"), Value::Map(map)) => {
+            for (k, v) in map.borrow().iter() {
+                let v = match v {
+                    // aria-hidden='false' means something
+                    Value::Bool(b) if prefix == "aria" => b.to_string().into(),
+                    v => v.clone(),
+                };
+                add_attr(attrs, classes, &format!("{}-{}", prefix, k), v)?;
+            }
+        }
+        (name, val) => {
+            check_attr_name(name)?;
+            attrs.insert(name, val);
+        }
+    }
+    Ok(())
+}
+
+/// Attribute names come from Maps and variables too, so make sure
+/// one can't end the attribute, or the tag, and start another.
+fn check_attr_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '<' | '>' | '/' | '=')
+        })
+    {
+        return error!("bad attribute name: {:?}", name);
+    }
+    Ok(())
+}
+
+/// `class` can be a String, a List of classes, or a Map of classes
+/// to whether they're on, nested however you like.
+fn add_class(classes: &mut Vec<String>, val: &Value) {
+    match val {
+        Value::List(list) => list.borrow().iter().for_each(|v| add_class(classes, v)),
+        Value::Map(map) => {
+            for (k, v) in map.borrow().iter() {
+                if v.to_bool() {
+                    classes.push(escape_attr(k));
+                }
+            }
+        }
+        val if val.to_bool() => classes.push(attr_value(val)),
+        _ => {}
+    }
+}
+
+/// Escape a Value for use in a tag's attribute, unless it's safe.
+fn attr_value(val: &Value) -> String {
+    match val {
+        // safe to print, but it still can't end the attribute early
+        Value::Safe(s) => s.replace('"', "&quot;"),
+        _ => escape_attr(&val.to_string()),
+    }
+}
+
+/// Lets us stream into a `fmt::Write`. A char split across two
+/// writes is held onto until the rest of it arrives.
+struct FmtWriter<'w> {
+    w: &'w mut dyn fmt::Write,
+    partial: Vec<u8>,
+}
+
+impl Write for FmtWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.partial.extend_from_slice(buf);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(s) => s.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+        let s = std::str::from_utf8(&self.partial[..valid]).unwrap_or_default();
+        self.w.write_str(s).map_err(io::Error::other)?;
+        self.partial.drain(..valid);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
@@ -0,0 +1,33 @@
+//! HTML escaping for Values printed into a template.
+
+/// Escape a string so it can be safely printed as text in the body
+/// of an HTML tag.
+///
+/// `escape_html("<b>Tom & Jerry</b>") #=> "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"`
+pub fn escape_html(s: &str) -> String {
+    escape(s, false)
+}
+
+/// Escape a string so it can be safely printed as a quoted attribute
+/// value.
+///
+/// `escape_attr("it's \"hot\"") #=> "it&#39;s &quot;hot&quot;"`
+pub fn escape_attr(s: &str) -> String {
+    escape(s, true)
+}
+
+/// Replace special characters with their HTML entities.
+fn escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\'' if attr => out.push_str("&#39;"),
+            '"' if attr => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,434 @@
+//! Lay out rendered HTML: as-is, pretty-printed, or minified.
+
+use std::{
+    io::{self, Write},
+    mem,
+};
+
+/// How `Env` lays out the HTML it renders.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A newline after each closing tag, and text as it was printed.
+    #[default]
+    Default,
+    /// One block-level tag or line of text per line, indented two
+    /// spaces per level of nesting. Inline tags like `<b>` stay on
+    /// the line with the text around them, and tags with only text
+    /// inside stay on one line.
+    Pretty,
+    /// No whitespace next to block-level tags, and runs of whitespace
+    /// in text or between inline tags collapsed to one space.
+    Minify,
+}
+
+/// Options for rendering templates. Set them with
+/// `Env::set_options()`:
+///
+/// ```
+/// use hatter::{Env, RenderOptions};
+///
+/// let mut env = Env::new();
+/// env.set_options(RenderOptions::minify());
+/// assert_eq!(
+///     "<ul><li>One</li><li>Two</li></ul>",
+///     env.render("<ul>\n  <li> One\n  <li> Two").unwrap()
+/// );
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub format: Format,
+    /// `<br />` instead of `<br>`. See `Env::set_xhtml()`.
+    pub xhtml: bool,
+}
+
+impl RenderOptions {
+    /// Indented output, for reading and snapshot tests.
+    pub fn pretty() -> RenderOptions {
+        RenderOptions {
+            format: Format::Pretty,
+            ..RenderOptions::default()
+        }
+    }
+
+    /// As few bytes as possible, for production.
+    pub fn minify() -> RenderOptions {
+        RenderOptions {
+            format: Format::Minify,
+            ..RenderOptions::default()
+        }
+    }
+
+    /// Same as `minify()`.
+    pub fn compact() -> RenderOptions {
+        RenderOptions::minify()
+    }
+}
+
+/// HTML5 elements that never have a closing tag.
+pub(crate) fn is_void(tagname: &str) -> bool {
+    matches!(
+        tagname,
+        "area"
+            | "base"
+            | "br"
+            | "col"
+            | "embed"
+            | "hr"
+            | "img"
+            | "input"
+            | "link"
+            | "meta"
+            | "param"
+            | "source"
+            | "track"
+            | "wbr"
+    )
+}
+
+/// Elements that start on a new line, so whitespace around them
+/// doesn't show up on the page. Everything else, including tags we
+/// don't know, is treated as inline.
+fn is_block(tagname: &str) -> bool {
+    matches!(
+        tagname,
+        "address"
+            | "article"
+            | "aside"
+            | "base"
+            | "blockquote"
+            | "body"
+            | "br"
+            | "caption"
+            | "col"
+            | "colgroup"
+            | "dd"
+            | "details"
+            | "dialog"
+            | "div"
+            | "dl"
+            | "dt"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "head"
+            | "header"
+            | "hr"
+            | "html"
+            | "legend"
+            | "li"
+            | "link"
+            | "main"
+            | "menu"
+            | "meta"
+            | "nav"
+            | "noscript"
+            | "ol"
+            | "optgroup"
+            | "option"
+            | "p"
+            | "pre"
+            | "script"
+            | "section"
+            | "style"
+            | "summary"
+            | "table"
+            | "tbody"
+            | "td"
+            | "template"
+            | "tfoot"
+            | "th"
+            | "thead"
+            | "title"
+            | "tr"
+            | "ul"
+    )
+}
+
+/// Elements whose bodies are written exactly as they are.
+fn is_raw(tagname: &str) -> bool {
+    matches!(tagname, "pre" | "script" | "style" | "textarea")
+}
+
+enum Token {
+    Tag,
+    Text,
+    Raw,
+}
+
+/// Reformats HTML as it's written, then passes it on to `w`. Call
+/// `finish()` once everything has been written.
+pub(crate) struct Formatter<'w> {
+    w: &'w mut dyn Write,
+    format: Format,
+    buf: Vec<u8>,         // input we haven't formatted yet
+    raw: Option<String>,  // inside <pre>, <script>, etc
+    depth: usize,         // how many tags deep we are
+    open: Option<String>, // opening tag we haven't printed yet
+    inline: String,       // text and inline tags on the current line
+    keep: bool,           // `inline` is raw, so keep its whitespace
+    space: bool,          // whitespace to write if inline content follows
+    block: bool,          // last thing written was a block-level tag
+}
+
+impl<'w> Formatter<'w> {
+    pub(crate) fn new(format: Format, w: &'w mut dyn Write) -> Formatter<'w> {
+        Formatter {
+            w,
+            format,
+            buf: vec![],
+            raw: None,
+            depth: 0,
+            open: None,
+            inline: String::new(),
+            keep: false,
+            space: false,
+            block: true,
+        }
+    }
+
+    /// Format and write whatever is left.
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.tokens(true)?;
+        self.flush_open()?;
+        self.flush_inline()?;
+        self.w.flush()
+    }
+
+    /// Format every complete tag and run of text in `buf`. Anything
+    /// that might continue in the next write is kept for later,
+    /// unless we're `done`.
+    fn tokens(&mut self, done: bool) -> io::Result<()> {
+        while !self.buf.is_empty() {
+            let buf = &self.buf;
+            let end = if let Some(name) = &self.raw {
+                find(buf, format!("</{}", name).as_bytes()).map(|i| (i, Token::Raw))
+            } else if buf[0] == b'<' && buf.get(1).is_none_or(|b| is_tag_start(*b)) {
+                if buf.starts_with(b"<!--") {
+                    find(buf, b"-->").map(|i| (i + 3, Token::Tag))
+                } else {
+                    tag_end(buf).map(|i| (i, Token::Tag))
+                }
+            } else {
+                text_end(buf).map(|i| (i, Token::Text))
+            };
+            let (len, token) = match end {
+                Some(end) => end,
+                None if !done => break,
+                None if self.raw.is_some() => (buf.len(), Token::Raw),
+                None => (buf.len(), Token::Text),
+            };
+
+            let bytes = self.buf.drain(..len).collect::<Vec<_>>();
+            let s = String::from_utf8_lossy(&bytes);
+            match token {
+                Token::Tag => self.tag(&s)?,
+                Token::Text => self.text(&s, false)?,
+                Token::Raw => {
+                    self.raw = None;
+                    self.text(&s, true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn tag(&mut self, tag: &str) -> io::Result<()> {
+        let name = tag
+            .trim_start_matches(['<', '/'])
+            .split(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let is_comment = tag.starts_with("<!--");
+        let block = (tag.starts_with("<!") && !is_comment) || is_block(&name);
+
+        if self.format == Format::Minify {
+            if mem::take(&mut self.space) && !block {
+                self.w.write_all(b" ")?;
+            }
+            if !is_comment {
+                self.block = block;
+            }
+            if !tag.starts_with("</") && !tag.ends_with("/>") && is_raw(&name) {
+                self.raw = Some(name);
+            }
+            return self.w.write_all(tag.as_bytes());
+        }
+
+        // inline tags go on the line with the text around them
+        if !block && !is_raw(&name) {
+            self.inline.push_str(tag);
+            return Ok(());
+        }
+
+        if tag.starts_with("</") {
+            match self.open.take() {
+                // nothing block-level inside, so keep it on one line
+                Some(open) => {
+                    let inline = self.take_inline();
+                    self.line(&format!("{}{}{}", open, inline, tag))
+                }
+                None => {
+                    self.flush_inline()?;
+                    self.depth = self.depth.saturating_sub(1);
+                    self.line(tag)
+                }
+            }
+        } else if tag.starts_with("<!") || tag.ends_with("/>") || is_void(&name) {
+            self.flush_open()?;
+            self.flush_inline()?;
+            self.line(tag)
+        } else {
+            self.flush_open()?;
+            self.flush_inline()?;
+            if is_raw(&name) {
+                self.raw = Some(name);
+            }
+            self.open = Some(tag.to_string());
+            Ok(())
+        }
+    }
+
+    fn text(&mut self, text: &str, raw: bool) -> io::Result<()> {
+        if raw {
+            if self.format == Format::Minify {
+                return self.w.write_all(text.as_bytes());
+            }
+            self.inline.push_str(text);
+            self.keep = true;
+            return Ok(());
+        }
+
+        let text = collapse(text);
+        if self.format == Format::Pretty {
+            let text = match self.inline.ends_with(' ') {
+                true => text.trim_start_matches(' '),
+                false => &text,
+            };
+            self.inline.push_str(text);
+            return Ok(());
+        }
+
+        // whitespace only matters between inline tags and text
+        let trimmed = text.trim_matches(' ');
+        if trimmed.is_empty() {
+            self.space |= !text.is_empty() && !self.block;
+            return Ok(());
+        }
+        if (mem::take(&mut self.space) || text.starts_with(' ')) && !self.block {
+            self.w.write_all(b" ")?;
+        }
+        self.w.write_all(trimmed.as_bytes())?;
+        self.block = false;
+        self.space = text.ends_with(' ');
+        Ok(())
+    }
+
+    /// Print the opening tag we've been holding onto, and any inline
+    /// content after it, now that we know it has block tags inside.
+    fn flush_open(&mut self) -> io::Result<()> {
+        if let Some(open) = self.open.take() {
+            self.line(&open)?;
+            self.depth += 1;
+            self.flush_inline()?;
+        }
+        Ok(())
+    }
+
+    /// Print the text and inline tags we've collected on their own line.
+    fn flush_inline(&mut self) -> io::Result<()> {
+        let inline = self.take_inline();
+        if inline.is_empty() {
+            return Ok(());
+        }
+        self.line(&inline)
+    }
+
+    /// The text and inline tags we've collected, trimmed unless raw.
+    fn take_inline(&mut self) -> String {
+        let inline = mem::take(&mut self.inline);
+        if mem::take(&mut self.keep) {
+            inline
+        } else {
+            inline.trim_matches(' ').to_string()
+        }
+    }
+
+    fn line(&mut self, s: &str) -> io::Result<()> {
+        for _ in 0..self.depth {
+            self.w.write_all(b"  ")?;
+        }
+        self.w.write_all(s.as_bytes())?;
+        self.w.write_all(b"\n")
+    }
+}
+
+impl<'w> Write for Formatter<'w> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        self.tokens(false)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+/// Can this byte come right after the `<` of a tag?
+fn is_tag_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'/' || b == b'!'
+}
+
+fn find(buf: &[u8], needle: &[u8]) -> Option<usize> {
+    buf.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Index just past the `>` that ends the tag at the start of `buf`.
+fn tag_end(buf: &[u8]) -> Option<usize> {
+    let mut quote = None;
+    for (i, b) in buf.iter().enumerate() {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(*b),
+            (Some(q), _) if q == *b => quote = None,
+            (None, b'>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the `<` that starts the next tag.
+fn text_end(buf: &[u8]) -> Option<usize> {
+    (1..buf.len()).find(|&i| buf[i] == b'<' && buf.get(i + 1).is_none_or(|b| is_tag_start(*b)))
+}
+
+/// Collapse each run of whitespace into one space.
+fn collapse(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut space = false;
+    for c in s.chars() {
+        if c.is_ascii_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            out.push(' ');
+            space = false;
+        }
+        out.push(c);
+    }
+    if space {
+        out.push(' ');
+    }
+    out
+}
@@ -0,0 +1,284 @@
+//! Zero-dependency JSON encoding and decoding for Values.
+
+use {
+    crate::{OMap, Result, Value},
+    std::{iter::Peekable, str::CharIndices},
+};
+
+/// Convert a Value to JSON. The output is safe to embed in a
+/// `<script>` tag or an attribute: `<`, `>`, `&`, and `'` are always
+/// written as `\u` escapes.
+///
+/// `to_json(["hi", 1, { ok: true }]) #=> ["hi",1,{"ok":true}]`
+pub fn to_json(val: &Value) -> Result<String> {
+    let mut out = String::new();
+    encode(val, &mut out)?;
+    Ok(out)
+}
+
+/// Parse JSON into a Value. Objects become Maps, arrays become Lists,
+/// and `null` becomes `Value::None`.
+pub fn parse_json(s: &str) -> Result<Value> {
+    let mut parser = Parser {
+        src: s,
+        chars: s.char_indices().peekable(),
+        depth: 0,
+    };
+    let val = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok(val),
+        Some((pos, c)) => error!("JSON error at {}: unexpected '{}'", pos, c),
+    }
+}
+
+fn encode(val: &Value, out: &mut String) -> Result<()> {
+    match val {
+        Value::None => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) if n.is_finite() => out.push_str(&n.to_string()),
+        Value::Number(..) => out.push_str("null"),
+        Value::String(s) | Value::Safe(s) => encode_str(s, out),
+        Value::List(list) => {
+            out.push('[');
+            for (i, v) in list.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                encode(v, out)?;
+            }
+            out.push(']');
+        }
+        Value::Map(map) => {
+            out.push('{');
+            for (i, (k, v)) in map.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                encode_str(k, out);
+                out.push(':');
+                encode(v, out)?;
+            }
+            out.push('}');
+        }
+        Value::Fn(..) | Value::Object(..) => {
+            return error!("can't convert {} to JSON", val.typename())
+        }
+    }
+    Ok(())
+}
+
+fn encode_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // keep </script> and friends from ending the <script> tag
+            '<' | '>' | '&' | '\'' | '\u{2028}' | '\u{2029}' => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deeply arrays and objects can be nested, so a pile of `[`s
+/// is an error instead of a stack overflow.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'s> {
+    src: &'s str,
+    chars: Peekable<CharIndices<'s>>,
+    depth: usize, // arrays and objects we're inside of
+}
+
+impl<'s> Parser<'s> {
+    /// Position of the next char, for errors.
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map(|(i, _)| *i).unwrap_or(self.src.len())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, ' ' | '\n' | '\r' | '\t'))) {
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((_, c)) if c == expected => Ok(()),
+            Some((pos, c)) => error!(
+                "JSON error at {}: expected '{}', got '{}'",
+                pos, expected, c
+            ),
+            None => error!("JSON error: expected '{}', got end of input", expected),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        let pos = self.pos();
+        match self.chars.peek().map(|(_, c)| *c) {
+            Some(c @ ('{' | '[')) => {
+                if self.depth == MAX_DEPTH {
+                    return error!("JSON error at {}: nested over {} deep", pos, MAX_DEPTH);
+                }
+                self.depth += 1;
+                let val = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                val
+            }
+            Some('"') => Ok(self.string()?.into()),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('n') => self.keyword("null", Value::None),
+            Some(c) => error!("JSON error at {}: unexpected '{}'", pos, c),
+            None => error!("JSON error: unexpected end of input"),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, val: Value) -> Result<Value> {
+        let pos = self.pos();
+        if self.src[pos..].starts_with(word) {
+            for _ in 0..word.len() {
+                self.chars.next();
+            }
+            Ok(val)
+        } else {
+            error!("JSON error at {}: expected {}", pos, word)
+        }
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.pos();
+        while matches!(
+            self.chars.peek(),
+            Some((_, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+        ) {
+            self.chars.next();
+        }
+        let end = self.pos();
+        match self.src[start..end].parse::<f64>() {
+            Ok(n) => Ok(Value::Number(n)),
+            Err(_) => error!(
+                "JSON error at {}: bad number {}",
+                start,
+                &self.src[start..end]
+            ),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, '/')) => out.push('/'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((pos, 'u')) => {
+                        let mut code = self.hex()?;
+                        // surrogate pair: a high surrogate, then a low one
+                        if (0xdc00..0xe000).contains(&code) {
+                            return error!("JSON error at {}: bad surrogate pair", pos);
+                        }
+                        if (0xd800..0xdc00).contains(&code) {
+                            self.expect('\\')?;
+                            if !matches!(self.chars.next(), Some((_, 'u'))) {
+                                return error!("JSON error at {}: bad surrogate pair", pos);
+                            }
+                            let low = self.hex()?;
+                            if !(0xdc00..0xe000).contains(&low) {
+                                return error!("JSON error at {}: bad surrogate pair", pos);
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => out.push(c),
+                            None => return error!("JSON error at {}: bad \\u escape", pos),
+                        }
+                    }
+                    Some((pos, c)) => return error!("JSON error at {}: bad escape \\{}", pos, c),
+                    None => return error!("JSON error: unclosed string"),
+                },
+                Some((_, c)) => out.push(c),
+                None => return error!("JSON error: unclosed string"),
+            }
+        }
+    }
+
+    /// Four hex digits, from a \u escape.
+    fn hex(&mut self) -> Result<u32> {
+        let pos = self.pos();
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next().and_then(|(_, c)| c.to_digit(16)) {
+                Some(d) => code = code * 16 + d,
+                None => return error!("JSON error at {}: bad \\u escape", pos),
+            }
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut list = vec![];
+        self.skip_whitespace();
+        if matches!(self.chars.peek(), Some((_, ']'))) {
+            self.chars.next();
+            return Ok(list.into());
+        }
+        loop {
+            list.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(list.into()),
+                Some((pos, c)) => return error!("JSON error at {}: unexpected '{}'", pos, c),
+                None => return error!("JSON error: unclosed array"),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut map = OMap::new();
+        self.skip_whitespace();
+        if matches!(self.chars.peek(), Some((_, '}'))) {
+            self.chars.next();
+            return Ok(Value::Map(map.into()));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            map.insert(key, self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Value::Map(map.into())),
+                Some((pos, c)) => return error!("JSON error at {}: unexpected '{}'", pos, c),
+                None => return error!("JSON error: unclosed object"),
+            }
+        }
+    }
+}
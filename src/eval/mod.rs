@@ -1,4 +1,7 @@
 pub mod args;
 pub mod builtin;
 pub mod env;
+pub mod escape;
+pub mod format;
+pub mod json;
 pub mod value;
@@ -8,6 +8,8 @@ use {
 
 mod from;
 mod inner;
+#[cfg(feature = "serde")]
+mod serialize;
 pub use inner::{Fn, List, Map, Native, Object, Special};
 
 /// Value is the dynamic type that represents an active Hatter object,
@@ -18,6 +20,7 @@ pub enum Value {
     Bool(bool),
     Number(f64),
     String(Symbol),
+    Safe(Symbol), // String that won't be HTML escaped.
     List(List),
     Map(Map),
     Fn(Fn),
@@ -28,8 +31,9 @@ impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Value::Number(num) => write!(f, "{}", num),
-            Value::String(s) => write!(f, "{}", s),
+            Value::String(s) | Value::Safe(s) => write!(f, "{}", s),
             Value::List(..) => write!(f, "{:?}", self),
+            Value::Object(o) => write!(f, "{}", o.to_string()),
             _ => write!(f, "{}", self.to_str()),
         }
     }
@@ -43,6 +47,7 @@ impl fmt::Debug for Value {
             Bool(b) => write!(f, "{}", b),
             Number(num) => write!(f, "{}", num),
             String(s) => write!(f, r#""{}""#, s),
+            Safe(s) => write!(f, r#"Safe("{}")"#, s),
             Fn(..) => f.debug_struct("Function").field("val", &"?").finish(),
             List(list) => write!(
                 f,
@@ -74,8 +79,8 @@ impl PartialEq for Value {
                     false
                 }
             }
-            Value::String(s) => {
-                if let Value::String(s2) = other {
+            Value::String(s) | Value::Safe(s) => {
+                if let Value::String(s2) | Value::Safe(s2) = other {
                     s == s2
                 } else {
                     false
@@ -101,7 +106,7 @@ impl PartialEq for Value {
 
 impl PartialEq<Value> for String {
     fn eq(&self, other: &Value) -> bool {
-        if let Value::String(s) = other {
+        if let Value::String(s) | Value::Safe(s) = other {
             s == self
         } else {
             false
@@ -118,11 +123,13 @@ impl Value {
         Some(self)
     }
 
+    /// Items in a List or Map, or chars in a String.
     pub fn len(&self) -> usize {
         match self {
             Value::List(list) => list.borrow().len(),
             Value::Map(map) => map.borrow().len(),
-            Value::String(s) => s.len(),
+            Value::String(s) | Value::Safe(s) => s.chars().count(),
+            Value::Object(o) => o.len(),
             _ => 0,
         }
     }
@@ -151,7 +158,7 @@ impl Value {
         use Value::*;
         match self {
             None => "",
-            String(s) => &s.to_str(),
+            String(s) | Safe(s) => s.to_str(),
             Number(..) => "(number)",
             Fn(..) => "{function}",
             List(..) => "(list)",
@@ -173,7 +180,7 @@ impl Value {
             None => "none",
             Bool(..) => "bool",
             Number(..) => "number",
-            String(..) => "string",
+            String(..) | Safe(..) => "string",
             Fn(..) => "fn",
             List(..) => "list",
             Map(..) => "map",
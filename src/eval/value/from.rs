@@ -2,6 +2,7 @@ use {
     crate::{Args, Fn, OMap, Result, Stmt, Symbol, Value},
     std::{
         collections::{BTreeMap, HashMap},
+        convert::TryFrom,
         rc::Rc,
     },
 };
@@ -66,6 +67,93 @@ into_number_as!(&isize, i32);
 into_bool!(bool);
 into_bool!(&bool);
 
+macro_rules! try_from_float {
+    ($type:ty) => {
+        impl TryFrom<Value> for $type {
+            type Error = crate::Error;
+            fn try_from(val: Value) -> Result<Self> {
+                match val {
+                    Value::Number(n) if n.is_finite() && n.abs() <= <$type>::MAX as f64 => {
+                        Ok(n as $type)
+                    }
+                    Value::Number(n) => Err(error_kind!(
+                        WrongArgType,
+                        "{} doesn't fit in {}",
+                        n,
+                        stringify!($type)
+                    )),
+                    _ => Err(error_kind!(WrongArgType, "Expected Number, got: {:?}", val)),
+                }
+            }
+        }
+    };
+}
+
+// `n as i32` would quietly turn 1.5 into 1, -5 into 0 for unsigned
+// types, and NaN into 0, so only convert whole numbers in range.
+macro_rules! try_from_int {
+    ($type:ty) => {
+        impl TryFrom<Value> for $type {
+            type Error = crate::Error;
+            fn try_from(val: Value) -> Result<Self> {
+                match val {
+                    // MAX + 1 is a power of two, so it's exact as an f64
+                    Value::Number(n)
+                        if n.fract() == 0.0
+                            && n >= <$type>::MIN as f64
+                            && n < <$type>::MAX as f64 + 1.0 =>
+                    {
+                        Ok(n as $type)
+                    }
+                    Value::Number(n) => Err(error_kind!(
+                        WrongArgType,
+                        "{} isn't a whole number that fits in {}",
+                        n,
+                        stringify!($type)
+                    )),
+                    _ => Err(error_kind!(WrongArgType, "Expected Number, got: {:?}", val)),
+                }
+            }
+        }
+    };
+}
+
+try_from_float!(f64);
+try_from_float!(f32);
+try_from_int!(i32);
+try_from_int!(i64);
+try_from_int!(u32);
+try_from_int!(u64);
+try_from_int!(usize);
+try_from_int!(isize);
+
+impl TryFrom<Value> for bool {
+    type Error = crate::Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::Bool(b) => Ok(b),
+            _ => Err(error_kind!(WrongArgType, "Expected Bool, got: {:?}", val)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Symbol {
+    type Error = crate::Error;
+    fn try_from(val: Value) -> Result<Self> {
+        match val {
+            Value::String(s) | Value::Safe(s) => Ok(s),
+            _ => Err(error_kind!(WrongArgType, "Expected String, got: {:?}", val)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = crate::Error;
+    fn try_from(val: Value) -> Result<Self> {
+        Symbol::try_from(val).map(|s| s.to_string())
+    }
+}
+
 impl From<&Value> for Value {
     fn from(v: &Value) -> Value {
         v.clone()
@@ -120,11 +208,7 @@ where
     V: Into<Value>,
 {
     fn from(map: BTreeMap<S, V>) -> Self {
-        let mut new = OMap::new();
-        for (k, v) in map {
-            new.insert(k.into(), v.into());
-        }
-        Value::Map(new.into())
+        Value::Map(OMap::from(map).into())
     }
 }
 
@@ -134,11 +218,7 @@ where
     V: Into<Value>,
 {
     fn from(map: HashMap<S, V>) -> Self {
-        let mut new = OMap::new();
-        for (k, v) in map {
-            new.insert(k.into(), val(v));
-        }
-        Value::Map(new.into())
+        Value::Map(OMap::from(map).into())
     }
 }
 
@@ -158,7 +238,7 @@ where
 impl From<Stmt> for Value {
     fn from(s: Stmt) -> Value {
         match s {
-            Stmt::Value(v) => v,
+            Stmt::Value(v) => v.into(),
             Stmt::List(x) => Value::from(x),
             Stmt::Map(x) => Value::from(x),
             _ => unimplemented!(),
@@ -2,8 +2,8 @@
 //! here.
 
 use {
-    crate::{Args, Env, OMap, Result, Scope, Stmt, Symbol, Value},
-    std::{cell::RefCell, ops::Deref, rc::Rc},
+    crate::{Args, Env, OMap, Result, Scope, Source, Stmt, Symbol, Value},
+    std::{cell::RefCell, mem, ops::Deref, rc::Rc, sync::Arc},
 };
 
 #[derive(Clone)]
@@ -59,13 +59,13 @@ impl From<OMap> for Map {
 }
 
 /// Three types of functions in Hatter:
-/// Fn: Function defined in Hatter.
+/// Fn: Function defined in Hatter, and the template it's from.
 /// Native: Function defined in Rust.
 /// Special: Function defined in Rust whose arguments aren't evaluated,
 /// like a macro.
 #[derive(Clone)]
 pub enum Fn {
-    Fn(Vec<Symbol>, Vec<Stmt>, Scope),
+    Fn(Vec<Symbol>, Vec<Stmt>, Scope, Option<Arc<Source>>), // params, body, closure, file
     Native(Rc<Native>),
     Special(Rc<Special>),
 }
@@ -91,4 +91,37 @@ pub trait Object {
 
     /// obj.KEY = VAL
     fn set(&self, key: &str, val: Value) {}
+
+    /// obj.METHOD(ARGS)
+    ///
+    /// By default, calls the function `get(METHOD)` returns.
+    fn call(&self, method: &str, mut args: Args) -> Result<Value> {
+        match self.get(method) {
+            Some(f @ Value::Fn(..)) => {
+                let vals = mem::take(&mut *args);
+                args.env.call(&f, vals)
+            }
+            _ => error!("{} has no method {}", self.typename(), method),
+        }
+    }
+
+    /// for x in obj
+    fn iter(&self) -> Option<Box<dyn Iterator<Item = Value> + '_>> {
+        None
+    }
+
+    /// print(obj)
+    fn to_string(&self) -> String {
+        "(object)".to_string()
+    }
+
+    /// len(obj)
+    fn len(&self) -> usize {
+        0
+    }
+
+    /// empty?(obj)
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
@@ -17,6 +17,8 @@
 #![allow(clippy::should_implement_trait)]
 #![allow(clippy::new_without_default)]
 #![allow(clippy::inherent_to_string)]
+#![allow(clippy::wrong_self_convention)]
+#![allow(clippy::derived_hash_with_manual_eq)]
 
 #[macro_use]
 mod macros;
@@ -25,35 +27,47 @@ mod error;
 mod compile;
 mod eval;
 mod interner;
+mod loader;
 mod omap;
 pub mod prelude;
 mod symbol;
 mod template;
+mod templates;
 
 #[cfg(feature = "repl")]
 pub mod repl;
 
+#[cfg(feature = "derive")]
+pub use hatter_derive::{hatter_methods, HatterObject};
+
 pub use {
     compile::{
-        compile,
-        lexer::scan,
-        parser::parse,
+        check, compile,
+        lexer::{scan, scan_all},
+        literal::Literal,
+        parser::{parse, parse_all},
+        span::Span,
         stmt::Stmt,
         syntax::{Syntax, SyntaxTrait},
         tag::Tag,
         token::Token,
     },
-    error::{line_and_col, print_error, Error, ErrorKind},
+    error::{line_and_col, print_error, Error, ErrorKind, Frame, Source},
     eval::{
         args::Args,
         builtin,
         env::{eval, render, Env, Jump, Scope},
+        escape::{escape_attr, escape_html},
+        format::{Format, RenderOptions},
+        json::{parse_json, to_json},
         value::{self, Fn, Native, Object, Special, Value},
     },
     interner::Interner,
+    loader::{FileLoader, Loader, MapLoader},
     omap::OMap,
     symbol::Symbol,
     template::Template,
+    templates::Templates,
 };
 
 /// Alias for a generic Result type.
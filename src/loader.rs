@@ -0,0 +1,133 @@
+//! Loaders find the source code of other templates, for `include()`,
+//! `import()`, and `extends`.
+
+use {
+    crate::{Result, Template},
+    std::{
+        collections::HashMap,
+        fmt, fs,
+        path::{Component, Path, PathBuf},
+        sync::Arc,
+    },
+};
+
+/// Implement this to load templates from wherever you keep them.
+pub trait Loader {
+    /// Return the source code of the template named `name`.
+    fn load(&self, name: &str) -> Result<String>;
+
+    /// Return the template named `name`. By default this compiles
+    /// what `load()` returns, but loaders that keep compiled
+    /// templates around, like `Templates`, can hand those out instead.
+    fn load_template(&self, name: &str) -> Result<Arc<Template>> {
+        Ok(Arc::new(Template::named(name, self.load(name)?)))
+    }
+}
+
+impl fmt::Debug for dyn Loader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Loader")
+    }
+}
+
+/// Loads templates from a directory on disk. The `.hat` extension is
+/// optional, so `include("nav")` will load `nav.hat`.
+#[derive(Debug)]
+pub struct FileLoader {
+    root: PathBuf,
+}
+
+impl FileLoader {
+    /// Load templates relative to `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> FileLoader {
+        FileLoader {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Default for FileLoader {
+    /// Load templates relative to the current directory.
+    fn default() -> FileLoader {
+        FileLoader::new(".")
+    }
+}
+
+impl Loader for FileLoader {
+    fn load(&self, name: &str) -> Result<String> {
+        check_name(name)?;
+        let mut path = self.root.join(name);
+        if path.extension().is_none() {
+            path.set_extension("hat");
+        }
+        fs::read_to_string(&path)
+            .map_err(|e| error_kind!(RuntimeError, "can't load {}: {}", path.display(), e))
+    }
+}
+
+/// Make sure `name` is relative and has no `..`, so templates can't
+/// wander outside of a loader's root directory.
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    if Path::new(name)
+        .components()
+        .all(|c| matches!(c, Component::Normal(..) | Component::CurDir))
+    {
+        Ok(())
+    } else {
+        error!("can't load {}: names must be relative, without ..", name)
+    }
+}
+
+/// The name of a template without `./` or `.hat`, so `nav`,
+/// `nav.hat`, and `./nav.hat` can be recognized as the same one.
+pub(crate) fn normalize(name: &str) -> String {
+    let name = name
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    match name.strip_suffix(".hat") {
+        Some(stem) => stem.to_string(),
+        None => name,
+    }
+}
+
+/// Loads templates from memory. Handy for tests, or for embedding
+/// templates in your binary with `include_str!()`. Like
+/// `FileLoader`, `nav`, `nav.hat`, and `./nav` are the same template.
+#[derive(Debug, Default)]
+pub struct MapLoader {
+    templates: HashMap<String, String>,
+}
+
+impl MapLoader {
+    /// Create an empty MapLoader.
+    pub fn new() -> MapLoader {
+        MapLoader::default()
+    }
+
+    /// Add a template named `name`.
+    pub fn insert<N: Into<String>, S: Into<String>>(&mut self, name: N, source: S) {
+        self.templates
+            .insert(normalize(&name.into()), source.into());
+    }
+}
+
+impl Loader for MapLoader {
+    fn load(&self, name: &str) -> Result<String> {
+        self.templates
+            .get(&normalize(name))
+            .cloned()
+            .ok_or_else(|| error_kind!(RuntimeError, "can't load {}: not found", name))
+    }
+}
+
+impl<N: Into<String>, S: Into<String>> From<HashMap<N, S>> for MapLoader {
+    fn from(map: HashMap<N, S>) -> MapLoader {
+        let mut loader = MapLoader::new();
+        for (name, source) in map {
+            loader.insert(name, source);
+        }
+        loader
+    }
+}
@@ -1,5 +1,5 @@
 use {
-    hatter::{parse, render, scan, Stmt, Syntax, Token},
+    hatter::{check, parse, scan, Env, FileLoader, Format, RenderOptions, Stmt, Syntax, Token},
     std::{
         env, fs,
         io::{self, Write},
@@ -8,7 +8,25 @@ use {
 };
 
 fn main() -> io::Result<()> {
-    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut options = RenderOptions::default();
+    let args = env::args()
+        .skip(1)
+        .filter(|arg| match arg.as_ref() {
+            "--pretty" => {
+                options.format = Format::Pretty;
+                false
+            }
+            "--minify" | "--compact" => {
+                options.format = Format::Minify;
+                false
+            }
+            "--xhtml" => {
+                options.xhtml = true;
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
 
     if args.is_empty() {
         #[cfg(feature = "repl")]
@@ -33,10 +51,7 @@ fn main() -> io::Result<()> {
     }
 
     if !matches!(command, "scan" | "parse" | "check" | "compile" | "print") {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("unknown command: {}", command),
-        ));
+        return Err(io::Error::other(format!("unknown command: {}", command)));
     }
 
     match path.as_ref() {
@@ -60,30 +75,53 @@ fn main() -> io::Result<()> {
 
     if command == "scan" {
         let tokens = scan(&source)
-            .map_err(|e| print_error(&path, &source, e))
+            .map_err(|e| print_error(path, &source, e))
             .unwrap();
         print_tokens(tokens);
         return Ok(());
     }
 
-    if command == "parse" || command == "check" {
+    if command == "check" {
+        let errors = check(&source);
+        if errors.is_empty() {
+            return Ok(());
+        }
+        let count = errors.len();
+        for err in errors {
+            hatter::print_error(path, &source, err);
+        }
+        eprintln!(
+            "{} error{} in {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            path
+        );
+        std::process::exit(1);
+    }
+
+    if command == "parse" {
         let ast = scan(&source)
             .and_then(|tokens| parse(&tokens))
-            .map_err(|e| print_error(&path, &source, e))
+            .map_err(|e| print_error(path, &source, e))
             .unwrap();
-        if command == "parse" {
-            print_ast(&ast);
-        }
+        print_ast(&ast);
         return Ok(());
     }
 
-    write!(
-        io::stdout(),
-        "{}",
-        render(&source)
-            .map_err(|e| print_error(&path, &source, e))
-            .unwrap()
-    )
+    // load other templates relative to this one
+    let mut env = Env::new();
+    env.set_options(options);
+    if let Some(dir) = path::Path::new(path).parent() {
+        env.set_loader(FileLoader::new(dir));
+    }
+
+    // render it all before writing anything, so an error doesn't
+    // leave half a page behind
+    let html = env
+        .render(&source)
+        .map_err(|e| print_error(path, &source, e))
+        .unwrap();
+    io::stdout().lock().write_all(html.as_bytes())
 }
 
 fn print_usage() -> io::Result<()> {
@@ -92,14 +130,19 @@ fn print_usage() -> io::Result<()> {
     #[cfg(feature = "repl")]
     let repl = "  repl        Start REPL. (default without args)\n";
     print!(
-        r#"Usage: hatter [COMMAND] <file.hat>
+        r#"Usage: hatter [COMMAND] [OPTIONS] <file.hat>
 
 Commands:
   scan        Print tokens.
   parse       Print AST.
   check       Check for syntax/parse errors only.
   print       Print HTML. (default)
-{}"#,
+{}
+Options:
+  --pretty    Indent HTML by nesting depth.
+  --minify    Remove whitespace between tags. (or --compact)
+  --xhtml     Self-close void elements, like <br />.
+"#,
         repl
     );
     Ok(())
@@ -2,10 +2,11 @@
 
 use {
     crate::{Symbol, Value},
-    std::collections::HashMap,
+    std::collections::{BTreeMap, HashMap},
 };
 
 /// A HashMap for Hatter that preserves insertion order.
+#[derive(Clone)]
 pub struct OMap {
     map: HashMap<Symbol, Value>,
     order: Vec<Symbol>,
@@ -24,6 +25,10 @@ impl OMap {
         self.map.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn get<S: Into<Symbol>>(&self, key: S) -> Option<&Value> {
         self.map.get(&key.into())
     }
@@ -31,16 +36,17 @@ impl OMap {
     pub fn insert<S: Into<Symbol>>(&mut self, key: S, val: Value) {
         debug_assert!(self.map.len() == self.order.len());
         let sym = key.into();
-        self.map.insert(sym.clone(), val);
-        self.order.push(sym);
+        if self.map.insert(sym.clone(), val).is_none() {
+            self.order.push(sym);
+        }
     }
 
-    pub fn remove<S: Into<Symbol>>(&mut self, key: S) {
+    pub fn remove<S: Into<Symbol>>(&mut self, key: S) -> Option<Value> {
         debug_assert!(self.map.len() == self.order.len());
         let sym = key.into();
-        self.map.remove(&sym);
-        self.order
-            .remove(self.order.iter().position(|s| s == &sym).unwrap());
+        let val = self.map.remove(&sym)?;
+        self.order.retain(|s| s != &sym);
+        Some(val)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&Symbol, &Value)> {
@@ -50,6 +56,40 @@ impl OMap {
     }
 }
 
+macro_rules! from_pairs {
+    ($type:ty) => {
+        impl<S: Into<Symbol>, V: Into<Value>> From<$type> for OMap {
+            fn from(pairs: $type) -> OMap {
+                let mut map = OMap::new();
+                for (k, v) in pairs {
+                    map.insert(k, v.into());
+                }
+                map
+            }
+        }
+
+        impl<S: AsRef<str>, V: Clone + Into<Value>> From<&$type> for OMap {
+            fn from(pairs: &$type) -> OMap {
+                let mut map = OMap::new();
+                for (k, v) in pairs {
+                    map.insert(Symbol::from(k.as_ref()), v.clone().into());
+                }
+                map
+            }
+        }
+    };
+}
+
+from_pairs!(HashMap<S, V>);
+from_pairs!(BTreeMap<S, V>);
+from_pairs!(Vec<(S, V)>);
+
+impl From<&OMap> for OMap {
+    fn from(map: &OMap) -> OMap {
+        map.clone()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,9 +98,9 @@ mod tests {
     fn test_insert() {
         let mut map = OMap::new();
         map.insert("name", "Cyndi L.".into());
-        assert_eq!("Cyndi L.", map.get(&Symbol::from("name")).unwrap().to_str());
+        assert_eq!("Cyndi L.", map.get(Symbol::from("name")).unwrap().to_str());
         map.remove("name");
-        assert_eq!(None, map.get(&Symbol::from("name")));
+        assert_eq!(None, map.get(Symbol::from("name")));
     }
 
     #[test]
@@ -74,4 +114,16 @@ mod tests {
         }
         assert_eq!("name: Bilbo Baggins\nage: 111\n", out);
     }
+
+    #[test]
+    fn test_insert_existing() {
+        let mut map = OMap::new();
+        map.insert("name", "Frodo".into());
+        map.insert("age", 33.into());
+        map.insert("name", "Sam".into());
+        assert_eq!(2, map.len());
+        let keys = map.iter().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(vec!["name", "age"], keys);
+        assert_eq!("Sam", map.get("name").unwrap().to_str());
+    }
 }
@@ -2,15 +2,12 @@ use {
     crate::Interner,
     std::{
         fmt, ops,
-        sync::{Once, RwLock},
+        sync::{OnceLock, RwLock},
     },
 };
 
 /// Our String cache, which our Symbols reference.
-static mut CACHE: Option<RwLock<Interner>> = None;
-
-/// Only setup the CACHE once.
-static SETUP: Once = Once::new();
+static CACHE: OnceLock<RwLock<Interner>> = OnceLock::new();
 
 /// A Symbol is an interned String, created and managed by
 /// `Interner` and stored in CACHE.
@@ -18,10 +15,7 @@ static SETUP: Once = Once::new();
 pub struct Symbol(u32);
 
 fn cache() -> Option<&'static RwLock<Interner>> {
-    unsafe {
-        SETUP.call_once(|| CACHE = Some(RwLock::new(Interner::new())));
-        CACHE.as_ref()
-    }
+    Some(CACHE.get_or_init(|| RwLock::new(Interner::new())))
 }
 
 impl Symbol {
@@ -2,39 +2,107 @@
 //! you're not reloading templates on each page view.
 
 use {
-    crate::{compile, Result, Stmt},
-    std::{fs::File, io::Read, path::Path},
+    crate::{compile, Env, Error, OMap, Result, Source, Stmt},
+    std::{
+        convert::TryFrom,
+        fmt,
+        fs::File,
+        io::{Read, Write},
+        path::Path,
+        sync::{Arc, OnceLock},
+    },
 };
 
 /// Compiled HTML template.
+///
+/// Templates are `Send + Sync`, so one can be compiled at startup,
+/// put in an `Arc`, and rendered from many threads at once. Each
+/// render gets its own `Env`.
+#[derive(Debug)]
 pub struct Template {
-    source: String,
-    compiled: Option<Vec<Stmt>>,
+    file: Arc<Source>, // our name and source, shared with errors
+    compiled: OnceLock<Vec<Stmt>>,
 }
 
 impl Template {
     pub fn new(source: String) -> Template {
         Template {
-            source,
-            compiled: None,
+            file: Arc::new(Source {
+                name: None,
+                code: source,
+            }),
+            compiled: OnceLock::new(),
         }
     }
 
-    pub fn stmts(&mut self) -> Result<&[Stmt]> {
+    /// A template with a name, like its path. Errors use the name to
+    /// say where they happened.
+    pub fn named<S: Into<String>>(name: S, source: String) -> Template {
+        Template {
+            file: Arc::new(Source {
+                name: Some(name.into()),
+                code: source,
+            }),
+            compiled: OnceLock::new(),
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.file.name.as_deref()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.file.code
+    }
+
+    pub fn stmts(&self) -> Result<&[Stmt]> {
         self.compile()?;
-        if let Some(stmts) = &self.compiled {
+        if let Some(stmts) = self.compiled.get() {
             Ok(stmts)
         } else {
             Ok(&[])
         }
     }
 
-    pub fn compile(&mut self) -> Result<()> {
-        if self.compiled.is_none() {
-            self.compiled = Some(compile(&self.source)?);
+    pub fn compile(&self) -> Result<()> {
+        if self.compiled.get().is_none() {
+            // if another thread beat us to it, its AST is just as good
+            let stmts = compile(&self.file.code).map_err(|e| e.in_source(&self.file))?;
+            let _ = self.compiled.set(stmts);
         }
         Ok(())
     }
+
+    /// Render into a String using the variables in `env`.
+    pub fn render(&self, env: &mut Env) -> Result<String> {
+        env.render_file(Some(self.file()), self.stmts()?)
+    }
+
+    /// Render into a String in a fresh `Env`, with a top-level
+    /// variable for each key in `context`: a `HashMap`, `BTreeMap`,
+    /// `Vec<(K, V)>`, or `OMap`.
+    pub fn render_with<C: Into<OMap>>(&self, context: C) -> Result<String> {
+        let mut env = Env::new();
+        env.set_context(context);
+        self.render(&mut env)
+    }
+
+    /// Render using the variables in `env`, writing HTML to `w` as
+    /// it's produced.
+    pub fn render_to<W: Write>(&self, env: &mut Env, w: &mut W) -> Result<()> {
+        env.render_file_to(Some(self.file()), self.stmts()?, w)
+    }
+
+    /// Like `render_to()`, but for a `fmt::Write` like a `String`
+    /// or a `fmt::Formatter`.
+    pub fn render_to_fmt<W: fmt::Write>(&self, env: &mut Env, w: &mut W) -> Result<()> {
+        env.render_file_to_fmt(Some(self.file()), self.stmts()?, w)
+    }
+
+    /// Our name and source, for errors.
+    pub(crate) fn file(&self) -> Arc<Source> {
+        self.file.clone()
+    }
 }
 
 impl From<String> for Template {
@@ -49,16 +117,20 @@ impl From<&str> for Template {
     }
 }
 
-impl From<&Path> for Template {
-    fn from(p: &Path) -> Template {
-        File::open(p).unwrap().into()
+impl TryFrom<&Path> for Template {
+    type Error = Error;
+    fn try_from(p: &Path) -> Result<Template> {
+        let mut s = String::new();
+        File::open(p)?.read_to_string(&mut s)?;
+        Ok(Template::named(p.display().to_string(), s))
     }
 }
 
-impl From<File> for Template {
-    fn from(mut f: File) -> Template {
+impl TryFrom<File> for Template {
+    type Error = Error;
+    fn try_from(mut f: File) -> Result<Template> {
         let mut s = String::new();
-        f.read_to_string(&mut s).unwrap();
-        Template::new(s)
+        f.read_to_string(&mut s)?;
+        Ok(Template::new(s))
     }
 }
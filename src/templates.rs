@@ -0,0 +1,146 @@
+//! A registry of named templates loaded from a directory.
+//!
+//! In debug builds templates are recompiled whenever their file
+//! changes on disk, so you can edit them without restarting your
+//! server. In release builds they're compiled once and cached.
+
+use {
+    crate::{
+        loader::{check_name, normalize},
+        Env, Loader, OMap, Result, Template,
+    },
+    std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock},
+        time::SystemTime,
+    },
+};
+
+/// Templates in a directory, by name. `users/show` is the template
+/// in `users/show.hat`.
+///
+/// `Templates` is also a `Loader`, so `include()`, `import()`, and
+/// `extends` get their templates from the cache too. Clones share
+/// the same cache.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    root: Arc<Path>,
+    cache: Arc<RwLock<HashMap<String, Cached>>>,
+}
+
+#[derive(Debug)]
+struct Cached {
+    template: Arc<Template>,
+    modified: Option<SystemTime>,
+}
+
+impl Templates {
+    /// Load and compile every `.hat` file in `root` and its
+    /// subdirectories.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Templates> {
+        let templates = Templates {
+            root: root.as_ref().into(),
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        };
+        let mut names = vec![];
+        find_templates(&templates.root, "", &mut names)?;
+        for name in names {
+            templates.read(&name)?;
+        }
+        Ok(templates)
+    }
+
+    /// Names of all the loaded templates, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self
+            .cache
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Get the template named `name`, loading it if it's new or
+    /// (in debug builds) has changed since it was last compiled.
+    /// `nav`, `nav.hat`, and `./nav` are all the same template.
+    pub fn get(&self, name: &str) -> Result<Arc<Template>> {
+        check_name(name)?;
+        let name = normalize(name);
+        if let Some(cached) = self.cache.read().unwrap().get(&name) {
+            if !cfg!(debug_assertions) || modified(&self.path(&name)) == cached.modified {
+                return Ok(cached.template.clone());
+            }
+        }
+        self.read(&name)
+    }
+
+    /// Render the template named `name`, with a top-level variable
+    /// for each key in `context`. `include()`, `import()`, and
+    /// `extends` look for templates in this registry.
+    pub fn render<C: Into<OMap>>(&self, name: &str, context: C) -> Result<String> {
+        let template = self.get(name)?;
+        let mut env = Env::new();
+        env.set_loader(self.clone());
+        env.set_context(context);
+        template.render(&mut env)
+    }
+
+    /// Path to the template named `name`.
+    fn path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{}.hat", name))
+    }
+
+    /// Read and compile a template, then cache it.
+    fn read(&self, name: &str) -> Result<Arc<Template>> {
+        let path = self.path(name);
+        let modified = modified(&path);
+        let source = fs::read_to_string(&path)
+            .map_err(|e| error_kind!(RuntimeError, "can't load {}: {}", path.display(), e))?;
+        let template = Template::named(name, source);
+        template.compile()?;
+        let template = Arc::new(template);
+        self.cache.write().unwrap().insert(
+            name.to_string(),
+            Cached {
+                template: template.clone(),
+                modified,
+            },
+        );
+        Ok(template)
+    }
+}
+
+impl Loader for Templates {
+    fn load(&self, name: &str) -> Result<String> {
+        Ok(self.get(name)?.source().to_string())
+    }
+
+    fn load_template(&self, name: &str) -> Result<Arc<Template>> {
+        self.get(name)
+    }
+}
+
+/// Last time a file was modified, if we can tell.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Collect the names of all `.hat` files in `dir`, recursively.
+fn find_templates(dir: &Path, prefix: &str, names: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let dir = path.file_name().unwrap_or_default().to_string_lossy();
+            find_templates(&path, &format!("{}{}/", prefix, dir), names)?;
+        } else if path.extension().is_some_and(|ext| ext == "hat") {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            names.push(format!("{}{}", prefix, stem));
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 #![allow(unused)]
 
-use hatter::{compile, eval, render, Value};
+use hatter::{compile, eval, parse_json, render, to_json, Value};
 
 #[macro_use]
 mod helpers;
@@ -17,6 +17,106 @@ fn test_internal_functions() {
     assert_eval!(r#"type('heyo')"#, "string");
 }
 
+#[test]
+fn test_escaping() {
+    assert_eval!(r#"raw("<b>")"#, Value::Safe("<b>".into()));
+    assert_eval!(r#"type(raw("<b>"))"#, "string");
+    assert_eval!(
+        r#"escape("<b>Tom & Jerry</b>")"#,
+        "&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"
+    );
+    assert_eval!(r#"raw("hi") == "hi""#, true);
+    assert_eval!(r#"concat(raw("<b>"), "&")"#, Value::Safe("<b>&amp;".into()));
+    assert_eval!(r#"raw("<b>") + "&""#, Value::Safe("<b>&amp;".into()));
+    assert_eval!(r#""&" + raw("<b>")"#, Value::Safe("&amp;<b>".into()));
+    assert_eval!(r#""<" + "b>""#, Value::String("<b>".into()));
+}
+
+#[test]
+fn test_error() {
+    assert_error!(r#"error("Panic!")"#);
+    assert_eval!(r#"try(error, "Panic!").err"#, "Panic!");
+    assert_eval!(r#"try(error, "Panic!").ok"#, none!());
+    assert_eval!(r#"try(concat, "a", "b").ok"#, "ab");
+    assert_eval!(r#"try(concat, "a", "b").err"#, none!());
+    assert_eval!(r#"try(fn(x) do x + 1, 1).ok"#, 2);
+    assert_eval!(r#"try(fn(x) do error(x), 'oops').err"#, "oops");
+    assert_eval!(r#"try(nope).err"#, "can't find var: nope");
+    assert_render!(
+        r#"
+def combine(a, b)
+    error("Panic!")
+    concat(a, b)
+ret := try(combine, "Mr.", "Robinson Crusoe")
+if ret.err
+    print(ret.err)
+"#,
+        "Panic!"
+    );
+}
+
+#[test]
+fn test_json() {
+    assert_eval!(r#"to-json({}.missing)"#, "null");
+    assert_eval!(r#"to-json([1, 2.5, true, "hi"])"#, r#"[1,2.5,true,"hi"]"#);
+    assert_eval!(
+        r#"to-json({ a: 1, b: { c: [] } })"#,
+        r#"{"a":1,"b":{"c":[]}}"#
+    );
+    assert_eval!(r#"to-json('say "hi"')"#, r#""say \"hi\"""#);
+    assert_eval!(
+        r#"to-json("</script><!-- & 'hi'")"#,
+        r#""\u003c/script\u003e\u003c!-- \u0026 \u0027hi\u0027""#
+    );
+    assert_eval!(r#"[1, 2].json"#, "[1,2]");
+    assert_eval!(r#"{ json: 'mine' }.json"#, "mine");
+    assert_eval!(r#"type(to-json(1))"#, "string");
+    assert_error!(r#"to-json(to-json)"#);
+
+    assert_eval!(r#"parse-json("null")"#, none!());
+    assert_eval!(
+        r#"parse-json(" [1, -2.5e1, false] ")"#,
+        list![num!(1), num!(-25), boo!(false)]
+    );
+    assert_eval!(r#"parse-json('{"a": {"b": "c"}}').a.b"#, "c");
+    assert_eval!(r#"parse-json(to-json({ s: '<"&>' })).s"#, r#"<"&>"#);
+    assert_error!(r#"parse-json("[1, 2")"#);
+    assert_error!(r#"parse-json("{a: 1}")"#);
+    assert_error!(r#"parse-json("[1] 2")"#);
+    assert_error!(r#"parse-json("tru")"#);
+    assert_error!(r#"to_json(1)"#);
+    assert_error!(r#"parse_json("1")"#);
+
+    // nesting is limited instead of overflowing the stack
+    let deep = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(parse_json(&deep).is_ok());
+    let deep = format!("{}{}", "[".repeat(129), "]".repeat(129));
+    assert!(parse_json(&deep).is_err());
+    assert!(parse_json(&"[{\"a\":".repeat(100_000)).is_err());
+
+    assert_eq!(
+        parse_json(r#""\u00e9\ud83c\udfa9\n""#).unwrap(),
+        Value::from("é🎩\n")
+    );
+    // broken surrogate pairs
+    assert_error!(r#"parse-json('"\\ud800\\u0041"')"#);
+    assert!(parse_json(r#""\ud800\u0041""#).is_err());
+    assert!(parse_json(r#""\ud800\ud800""#).is_err());
+    assert!(parse_json(r#""\udc00""#).is_err());
+    assert!(parse_json(r#""\udfff\udc00""#).is_err());
+    assert!(parse_json(r#""\ud800""#).is_err());
+    assert!(parse_json(r#""\ud800x""#).is_err());
+    assert_eq!(
+        to_json(&Value::from("\u{2028}\u{1}\\")).unwrap(),
+        r#""\u2028\u0001\\""#
+    );
+
+    assert_render!(
+        r#"<script> "var X = {['</script>'].json};""#,
+        "<script>var X = [\"\\u003c/script\\u003e\"];</script>\n"
+    );
+}
+
 #[test]
 fn test_boolean_operators() {
     assert_eval!("true && true", true);
@@ -71,6 +171,170 @@ fn test_string() {
     assert_eval!(r#"count("mr rogers", "z")"#, 0);
 }
 
+#[test]
+fn test_string_library() {
+    assert_eval!("trim('  hi there ')", "hi there");
+    assert_eval!("trim('--hi--', '-')", "hi");
+    assert_eval!("ltrim('  hi  ')", "hi  ");
+    assert_eval!("rtrim('  hi  ')", "  hi");
+    assert_eval!("rtrim('hi!?!', '!?')", "hi");
+    assert_eval!("starts-with?('Mr Rogers', 'Mr')", true);
+    assert_eval!("starts-with?('Mr Rogers', 'Ro')", false);
+    assert_eval!("ends-with?('Mr Rogers', 'ers')", true);
+    assert_eval!("join(['a', 'b', 3], ', ')", "a, b, 3");
+    assert_eval!("join(['a', 'b'])", "ab");
+    assert_error!("join('ab')");
+
+    assert_eval!("capitalize('hELLO there')", "Hello there");
+    assert_eval!("capitalize('éclair')", "Éclair");
+    assert_eval!("capitalize('')", "");
+    assert_eval!(
+        "titlecase('the WIND in  the willows')",
+        "The Wind In  The Willows"
+    );
+
+    assert_eval!("truncate('Once upon a time', 10)", "Once up...");
+    assert_eval!("truncate('Once upon a time', 10, '…')", "Once upon…");
+    assert_eval!("truncate('Once upon a time', 9, '…')", "Once upo…");
+    assert_eval!("truncate('short', 10)", "short");
+    assert_eval!("truncate('héllo wörld', 5, '')", "héllo");
+    assert_eval!("truncate('hello', 2)", "..");
+
+    assert_eval!("pad-left('7', 3, '0')", "007");
+    assert_eval!("pad-left('ü', 3)", "  ü");
+    assert_eval!("pad-right('ab', 5, '.-')", "ab.-.");
+    assert_eval!("pad-right('abcdef', 3)", "abcdef");
+    assert_eval!("repeat('ab', 3)", "ababab");
+    assert_eval!("repeat('ab', 0)", "");
+    assert_error!("repeat('ab', 1e19)");
+    assert_error!("repeat('ab', 9007199254740991)");
+    assert_error!("pad-left('7', 1e19)");
+    assert_error!("pad-right('7', 9007199254740991, '.-')");
+
+    assert_eval!("slice('héllo', 1, 3)", "él");
+    assert_eval!("slice('héllo', -3)", "llo");
+    assert_eval!("slice('héllo', 3, 1)", "");
+    assert_eval!("slice('héllo', 2, 100)", "llo");
+    assert_eval!("slice([1, 2, 3, 4], -2)", list![num!(3), num!(4)]);
+    assert_eval!("slice([1, 2, 3, 4], 1, -1)", list![num!(2), num!(3)]);
+
+    assert_eval!(
+        "lines(\"one\\ntwo\\r\\nthree\")",
+        list![string!("one"), string!("two"), string!("three")]
+    );
+    assert_eval!("slugify('  Hello, Wörld! 2 ')", "hello-wörld-2");
+    assert_eval!("slugify('--')", "");
+    assert_eval!(
+        "strip-tags('<p class=x>Hi <b>there</b><!-- <b> --></p> 1 < 2')",
+        "Hi there 1 < 2"
+    );
+    assert_eval!(
+        "wordwrap('The quick brown fox jumps', 10)",
+        "The quick\nbrown fox\njumps"
+    );
+    assert_eval!(
+        "wordwrap('a verylongword b', 4, '<br>')",
+        "a<br>verylongword<br>b"
+    );
+    assert_eval!("wordwrap('one\\ntwo three', 20)", "one\ntwo three");
+    assert_eval!("'  Bob ' | trim | pad-left(5, '*')", "**Bob");
+}
+
+#[test]
+fn test_collections() {
+    assert_eval!(
+        "map([1, 2, 3], fn(x) x * 2)",
+        list![num!(2), num!(4), num!(6)]
+    );
+    assert_eval!(
+        "map({ a: 1, b: 2 }, fn(k, v) concat(k, v))",
+        list![string!("a1"), string!("b2")]
+    );
+    assert_eval!("filter(1..=5, fn(x) x % 2 == 0)", list![num!(2), num!(4)]);
+    assert_eval!(
+        "filter({ a: 1, b: 2 }, fn(k, v) v > 1)",
+        map!("b" => num!(2))
+    );
+    assert_eval!("reduce([1, 2, 3], fn(sum, x) sum + x)", 6);
+    assert_eval!("reduce(['a', 'b'], fn(s, x) s + x, '>')", ">ab");
+    assert_eval!("reduce([], fn(s, x) s + x)", none!());
+    assert_error!("map([1], fn(x, y) x)");
+    assert_error!("map([1], 'nope')");
+
+    assert_eval!("sort([3, 1, 2])", list![num!(1), num!(2), num!(3)]);
+    assert_eval!(
+        "sort(['b', 10, 'a', 9])",
+        list![num!(9), num!(10), string!("a"), string!("b")]
+    );
+    assert_eval!(
+        "sort-by(['ccc', 'a', 'bb'], len)",
+        list![string!("a"), string!("bb"), string!("ccc")]
+    );
+    assert_eval!("reverse([1, 2, 3])", list![num!(3), num!(2), num!(1)]);
+    assert_eval!("reverse('héllo')", "olléh");
+    assert_eval!("uniq([1, 2, 1, 3, 2])", list![num!(1), num!(2), num!(3)]);
+    assert_eval!(
+        "group-by(['ab', 'c', 'de'], len)",
+        map!(
+            "2" => list![string!("ab"), string!("de")],
+            "1" => list![string!("c")],
+        )
+    );
+
+    assert_eval!("find([1, 2, 3], fn(x) x > 1)", 2);
+    assert_eval!("find([1, 2, 3], fn(x) x > 5)", none!());
+    assert_eval!("any?([false, 1])", true);
+    assert_eval!("any?([1, 2], fn(x) x > 5)", false);
+    assert_eval!("all?([true, 1])", true);
+    assert_eval!("all?([1, 2], fn(x) x > 1)", false);
+    assert_eval!("all?([])", true);
+
+    assert_eval!("first([1, 2, 3])", 1);
+    assert_eval!("first([])", none!());
+    assert_eval!("first('héllo')", "h");
+    assert_eval!("last([1, 2, 3])", 3);
+    assert_eval!("last('héllo')", "o");
+    assert_eval!("take([1, 2, 3], 2)", list![num!(1), num!(2)]);
+    assert_eval!("take('héllo', 2)", "hé");
+    assert_eval!("drop([1, 2, 3], 2)", list![num!(3)]);
+    assert_eval!("drop('héllo', 10)", "");
+    assert_eval!(
+        "zip([1, 2, 3], ['a', 'b'])",
+        list![list![num!(1), string!("a")], list![num!(2), string!("b")]]
+    );
+    assert_eval!(
+        "flatten([1, [2, [3]], 4])",
+        list![num!(1), num!(2), num!(3), num!(4)]
+    );
+    assert_eval!(
+        "[3, 1, 2, 3] | uniq | sort | map(fn(x) x * 10) | reverse",
+        list![num!(30), num!(20), num!(10)]
+    );
+
+    assert_eval!("keys({ a: 1, b: 2 })", list![string!("a"), string!("b")]);
+    assert_eval!("values({ a: 1, b: 2 })", list![num!(1), num!(2)]);
+    assert_error!("keys([1])");
+    assert_eval!(
+        "merge({ a: 1, b: 2 }, { b: 3 }, { c: 4 })",
+        map!("a" => num!(1), "b" => num!(3), "c" => num!(4))
+    );
+    assert_eval!("has-key?({ a: 1 }, 'a')", true);
+    assert_eval!("has-key?({ a: 1 }, 'b')", false);
+
+    assert_eval!("a := [1, 2, 3]; remove(a, -1)", 3);
+    assert_eval!("a := [1, 2, 3]; remove(a, 0); a", list![num!(2), num!(3)]);
+    assert_eval!("a := [1, 2, 3]; remove(a, 5)", none!());
+    assert_eval!(
+        "m := { a: 1, b: 2 }; remove(m, 'a'); m",
+        map!("b" => num!(2))
+    );
+    assert_eval!("m := { a: 1 }; remove(m, 'zz')", none!());
+
+    assert_eval!("first := 'one'; first", "one");
+    assert_eval!("def map(x) do x\nmap(2)", 2);
+    assert_error!("x := 1; x := 2");
+}
+
 #[test]
 fn test_container() {
     assert_eval!("[1,2,3][1]", 2);
@@ -82,6 +346,9 @@ fn test_container() {
     assert_eval!("len([])", 0);
     assert_eval!("len({})", 0);
     assert_eval!("len({one: 1, two: 2})", 2);
+    assert_eval!("len('héllo')", 5);
+    assert_eval!("s := 'héllo wörld'; slice(s, 0, len(s))", "héllo wörld");
+    assert_eval!("len(raw('→'))", 1);
     assert_eval!("empty?({one: 1, two: 2})", false);
     assert_eval!("empty?({})", true);
     assert_eval!("empty?([])", true);
@@ -1,6 +1,11 @@
 #![allow(unused)]
+#![allow(clippy::approx_constant)]
+#![allow(clippy::four_forward_slashes)]
 
-use hatter::{compile, eval, render, Value};
+use hatter::{
+    compile, eval, render, Args, Env, FileLoader, Format, MapLoader, Object, RenderOptions, Result,
+    Value,
+};
 
 #[macro_use]
 mod helpers;
@@ -17,6 +22,18 @@ fn it_works() {
 fn test_string() {
     assert_render!(r#"<b> "Hi there""#, "<b>Hi there</b>\n");
     assert_render!(r#"<b> """#, "<b></b>\n");
+
+    // words that name builtins are text, unless the template set them
+    assert_render!(
+        "<p> join us, trim the lines, repeat",
+        "<p>join us, trim the lines, repeat</p>\n"
+    );
+    assert_render!("trim := 'cut'\n<p> trim", "<p>cut</p>\n");
+    assert_render!(
+        "<ul>\n  <li> map\n  <li> <b> first\n<table> <tr> <td> values",
+        "<ul><li>map</li>\n<li><b>first</b>\n</li>\n</ul>\n<table><tr><td>values</td>\n</tr>\n</table>\n"
+    );
+    assert_render!("map := 'mine'\n<li> map", "<li>mine</li>\n");
 }
 
 #[test]
@@ -45,8 +62,271 @@ for k, v in map
 
 #[test]
 fn test_attributes() {
-    assert_render!(r#"<div data-id='123'/>"#, "<div data-id='123' />\n");
-    assert_render!(r#"<div data-id=123/>"#, "<div data-id=123 />\n");
+    assert_render!(r#"<div data-id='123'/>"#, "<div data-id=\"123\"></div>\n");
+    assert_render!(r#"<div data-id=123/>"#, "<div data-id=\"123\"></div>\n");
+    assert_render!(
+        "<form GET='/search' autocomplete='off'>",
+        "<form method=\"GET\" action=\"/search\" autocomplete=\"off\"></form>\n"
+    );
+}
+
+#[test]
+fn test_structured_attributes() {
+    assert_render!(
+        "on := true\n<p.btn class={{active: on, hidden: false}}> x",
+        "<p class=\"btn active\">x</p>\n"
+    );
+    assert_render!(
+        "<p class=['a', ['b', {c: true}], when(false, 'd')]> x",
+        "<p class=\"a b c\">x</p>\n"
+    );
+    assert_render!(
+        "<p style={{color: 'red', 'font-size': '12px', display: false}}> x",
+        "<p style=\"color: red; font-size: 12px\">x</p>\n"
+    );
+    assert_render!(
+        "<p data={{id: 3, open: true, off: false, tags: ['a']}}> x",
+        "<p data-id=\"3\" data-open data-tags=\"[&quot;a&quot;]\">x</p>\n"
+    );
+    assert_render!(
+        "<p aria={{hidden: false, label: 'Hi'}}> x",
+        "<p aria-hidden=\"false\" aria-label=\"Hi\">x</p>\n"
+    );
+    assert_render!(
+        "attrs := { class: ['x', 'y'], data: { id: 1 } }\n<p.a ...attrs> x",
+        "<p class=\"a x y\" data-id=\"1\">x</p>\n"
+    );
+    assert_render!(
+        "<p style={{'--gap': '1em', '-webkit-box-flex': 1}}> x",
+        "<p style=\"--gap: 1em; -webkit-box-flex: 1\">x</p>\n"
+    );
+
+    // Map keys can't inject markup or other CSS
+    let mut env = Env::new();
+    for key in ["a><img src=x onerror=alert(1)>", "a b", "a=b", "a\""] {
+        let map = format!("{{{}: 1}}", hatter::to_json(&Value::from(key)).unwrap());
+        env.set("map", hatter::parse_json(&map).unwrap());
+        assert!(env.render("<div data={map}>").is_err(), "{:?}", key);
+        assert!(env.render("<div aria={map}>").is_err(), "{:?}", key);
+    }
+    for key in ["color: red; background", "a:b", "a b", "a;", ""] {
+        let map = format!("{{{}: 1}}", hatter::to_json(&Value::from(key)).unwrap());
+        env.set("map", hatter::parse_json(&map).unwrap());
+        assert!(env.render("<div style={map}>").is_err(), "{:?}", key);
+    }
+}
+
+#[test]
+fn test_attribute_spread() {
+    assert_render!(
+        "extra := { class: 'big', href: '/x', 'data-id': 3 }
+<a.btn href='/old' ...extra> Go",
+        "<a class=\"btn big\" href=\"/x\" data-id=\"3\">Go</a>\n"
+    );
+    // later attributes win, classes merge
+    assert_render!(
+        "extra := { class: 'big', href: '/x' }
+<a.btn {extra} class='red' href='/new'> Go",
+        "<a class=\"btn big red\" href=\"/new\">Go</a>\n"
+    );
+    assert_render!(
+        "off := { disabled: false }\n<input disabled ...off>",
+        "<input>\n"
+    );
+    // a String spreads as an attribute name
+    assert_render!("flag := 'checked'\n<input {flag}>", "<input checked>\n");
+    assert!(render("<p ...1> hi").is_err());
+
+    // names that would break out of the tag are errors
+    let err = render(
+        r#"attrs := parse-json('{"x onmouseover=alert(1) y": "v", "><script>alert(1)</script": true}')
+<div ...attrs> hi"#,
+    )
+    .unwrap_err();
+    assert!(err.details.contains("bad attribute name"), "{}", err);
+    let mut env = Env::new();
+    for name in [
+        "a b", "a\"b", "a'b", "a<b", "a>b", "a/b", "a=b", "a\tb", "a\u{0}b", "",
+    ] {
+        let key = hatter::to_json(&Value::from(name)).unwrap();
+        env.set("name", name);
+        env.set(
+            "attrs",
+            hatter::parse_json(&format!("{{{}: 1}}", key)).unwrap(),
+        );
+        assert!(env.render("<div {name}>").is_err(), "{:?}", name);
+        assert!(env.render("<div ...attrs>").is_err(), "{:?}", name);
+    }
+    assert_render!(
+        "attrs := { 'hx-get': '/x', '@click': 'go', 'x:y': 1 }\n<div ...attrs>",
+        "<div hx-get=\"/x\" @click=\"go\" x:y=\"1\"></div>\n"
+    );
+
+    // wrapper components can forward whatever they're given
+    assert_render!(
+        "def Field(label, attrs)
+    <label> label
+    <input.field ...attrs>
+<Field.wide label='Name' name='name' required>",
+        "<label>Name</label>\n<input class=\"field wide\" name=\"name\" required>\n"
+    );
+}
+
+#[test]
+fn test_void_elements() {
+    assert_render!("<br>", "<br>\n");
+    assert_render!("<hr/>", "<hr>\n");
+    // HTML5 has no self-closing tags but the void ones
+    assert_render!("<p/>", "<p></p>\n");
+    assert_render!("<script src='/a.js'/>", "<script src=\"/a.js\"></script>\n");
+    assert_render!("<img src='/a.png'>", "<img src=\"/a.png\">\n");
+    // text after a void element isn't inside it
+    assert_render!(
+        "<label> <input:checkbox@ok> OK",
+        "<label><input type=\"checkbox\" name=\"ok\">\nOK</label>\n"
+    );
+    assert_render!(
+        r#"<input value='say "hi"'>"#,
+        "<input value=\"say &quot;hi&quot;\">\n"
+    );
+
+    let mut env = Env::new();
+    env.set_xhtml(true);
+    assert_eq!("<br />\n", env.render("<br>").unwrap());
+    assert_eq!(
+        "<input type=\"checkbox\" checked=\"checked\" />\n",
+        env.render("<input:checkbox checked>").unwrap()
+    );
+    assert_eq!("<p />\n", env.render("<p/>").unwrap());
+}
+
+#[test]
+fn test_render_options() {
+    let src = r#"
+<ul#nav>
+    <li> <a href="/"> Home
+    <li.on>
+        "About   us"
+        <br>
+        <b> "!"
+<pre> "  keep\n    this"
+"#;
+
+    let mut env = Env::new();
+    env.set_options(RenderOptions::pretty());
+    assert_eq!(
+        r#"<ul id="nav">
+  <li><a href="/">Home</a></li>
+  <li class="on">
+    About us
+    <br>
+    <b>!</b>
+  </li>
+</ul>
+<pre>  keep
+    this</pre>
+"#,
+        env.render(src).unwrap()
+    );
+
+    env.set_options(RenderOptions::minify());
+    assert_eq!(
+        r#"<ul id="nav"><li><a href="/">Home</a></li><li class="on">About us<br><b>!</b></li></ul><pre>  keep
+    this</pre>"#,
+        env.render(src).unwrap()
+    );
+    assert_eq!(RenderOptions::minify(), RenderOptions::compact());
+    assert_eq!(
+        "<div><script src=\"/a.js\"></script><p>hi</p></div>",
+        env.render("<div>\n  <script src='/a.js'/>\n  <p> hi")
+            .unwrap()
+    );
+
+    // spaces between inline tags are kept, but not next to blocks
+    let inline = "<p>\n  <b> a\n  \" \"\n  <i> b\n  <br>\n  <em> c";
+    assert_eq!(
+        "<p><b>a</b> <i>b</i><br><em>c</em></p>",
+        env.render(inline).unwrap()
+    );
+    assert_eq!(
+        "<p>Hello <b>world</b>!</p>",
+        env.render("<p> raw('Hello <b>world</b>!')").unwrap()
+    );
+    assert_eq!(
+        "<p>a <!-- c --> b</p>",
+        env.render("<p> raw('a <!-- c --> b')").unwrap()
+    );
+
+    env.set_options(RenderOptions::pretty());
+    assert_eq!(
+        "<div>\n  <script src=\"/a.js\"></script>\n  <p>hi</p>\n</div>\n",
+        env.render("<div>\n  <script src='/a.js'/>\n  <p> hi")
+            .unwrap()
+    );
+    // inline tags stay on the line with their text
+    assert_eq!(
+        "<p>Hello <b>world</b>!</p>\n",
+        env.render("<p> raw('Hello <b>world</b>!')").unwrap()
+    );
+    assert_eq!(
+        "<p>\n  <b>a</b> <i>b</i>\n  <br>\n  <em>c</em>\n</p>\n",
+        env.render(inline).unwrap()
+    );
+
+    env.set_options(RenderOptions::pretty());
+    env.set_xhtml(true);
+    assert_eq!(
+        "<div>\n  <p>OK<input type=\"checkbox\" checked=\"checked\" /></p>\n</div>\n",
+        env.render("<div> <p> OK <input:checkbox checked>").unwrap()
+    );
+    assert_eq!(
+        RenderOptions {
+            format: Format::Pretty,
+            xhtml: true
+        },
+        env.options()
+    );
+
+    // streamed output is formatted the same way
+    let mut out = vec![];
+    env.set_options(RenderOptions::minify());
+    env.render_to("<div>\n    <p> One\n    <p> Two", &mut out)
+        .unwrap();
+    assert_eq!(
+        "<div><p>One</p><p>Two</p></div>",
+        String::from_utf8(out).unwrap()
+    );
+}
+
+#[test]
+fn test_escaping() {
+    assert_render!(
+        r#"name := "<script>alert('hi')</script>"
+<p> name"#,
+        "<p>&lt;script&gt;alert('hi')&lt;/script&gt;</p>\n"
+    );
+    assert_render!(
+        r#"name := "Tom & Jerry"
+<p> "<b>Hi</b> {name}!""#,
+        "<p><b>Hi</b> Tom &amp; Jerry!</p>\n"
+    );
+    assert_render!(
+        r#"title := "it's <here>"
+<div#{title}.{title} title=title>"#,
+        "<div id=\"it&#39;s &lt;here&gt;\" class=\"it&#39;s &lt;here&gt;\" title=\"it&#39;s &lt;here&gt;\"></div>\n"
+    );
+    assert_render!(r#"<p> "&lt;p&gt;""#, "<p>&lt;p&gt;</p>\n");
+    assert_render!(r#"puts("<b>")"#, "&lt;b&gt;\n");
+    assert_render!(
+        r#"html := "<b>Bold</b>"
+<p> raw(html)"#,
+        "<p><b>Bold</b></p>\n"
+    );
+    assert_render!(
+        r#"link := fn(url) return <a href=url> url
+<p> link("/?a=1&b=2")"#,
+        "<p><a href=\"/?a=1&amp;b=2\">/?a=1&amp;b=2</a>\n</p>\n"
+    );
 }
 
 #[test]
@@ -225,6 +505,27 @@ greet(name: 'Bob', title: 'Dr')
 "#,
         "Hiya, Dr. Bob!"
     );
+    assert_error!("def f(a) do a\nf(b: 1)");
+}
+
+#[test]
+fn test_pipe() {
+    assert_render!(
+        r#"
+def wrap(s, left, right) do return "{left}{s}{right}"
+name := '<bob>'
+<p> name | to-uppercase
+<p> "Hi {name | to-uppercase | wrap('(', ')')}"
+<p> 1 + 2 | wrap('[', ']')
+<p>
+    name
+        | split('b')
+        | len
+"#,
+        "<p>&lt;BOB&gt;</p>\n<p>Hi (&lt;BOB&gt;)</p>\n<p>[3]</p>\n<p>3</p>\n"
+    );
+    assert_eval!("'hi' | fn(s) s + '!'", string!("hi!"));
+    assert!(compile("1 | wrap(left: 1)").is_err());
 }
 
 #[test]
@@ -239,7 +540,7 @@ def total(x)
 total(5)
         "#,
         15
-    )
+    );
 }
 
 #[test]
@@ -286,8 +587,8 @@ fn test_nesting() {
 <#footer>
     <p> Thanks
 "#,
-        "<div id='main'><ul><li>1\n</li>\n<li>2\n</li>\n<li>3\n</li>\n</ul>\n</div>
-<div id='footer'><p>Thanks\n</p>\n</div>
+        "<div id=\"main\"><ul><li>1\n</li>\n<li>2\n</li>\n<li>3\n</li>\n</ul>\n</div>
+<div id=\"footer\"><p>Thanks\n</p>\n</div>
 "
     );
 }
@@ -296,13 +597,455 @@ fn test_nesting() {
 fn test_form_shortcuts() {
     assert_render!(
         "<form POST='/update'> <input:submit/>",
-        "<form method='POST' action='/update'><input type='submit' />\n</form>\n"
+        "<form method=\"POST\" action=\"/update\"><input type=\"submit\">\n</form>\n"
     );
 
     assert_render!(
         "<form GET='/search'> <input@query:text/> <input:submit/>",
-        "<form method='GET' action='/search'><input name='query' type='text' />\n<input type='submit' />\n</form>\n"
+        "<form method=\"GET\" action=\"/search\"><input name=\"query\" type=\"text\">\n<input type=\"submit\">\n</form>\n"
+    );
+}
+
+#[test]
+fn test_components() {
+    let card = "def Card(title, children, footer)
+    <div.card>
+        <h2> title
+        children
+        if footer
+            <footer> footer
+";
+    assert_render!(
+        format!(
+            "{}<Card title='Hi'>\n    <p> Body\n    <slot@footer> Bye",
+            card
+        ),
+        "<div class=\"card\"><h2>Hi</h2>\n<p>Body</p>\n<footer>Bye</footer>\n</div>\n"
+    );
+    assert_render!(
+        format!("{}<Card title='Hi' />", card),
+        "<div class=\"card\"><h2>Hi</h2>\n</div>\n"
+    );
+
+    // attrs collects whatever the other params didn't take
+    assert_render!(
+        "def Button(attrs, children) do <button type={attrs.type} class={attrs.class}> children
+<Button.big:submit> Go",
+        "<button type=\"submit\" class=\"big\">Go</button>\n"
+    );
+
+    // the body can see the caller's vars, and the component can return
+    // what it renders instead of printing it
+    assert_render!(
+        "Link := fn(href, children) return <a href={href}> children
+name := 'Bob'
+<Link href='/bob'> name",
+        "<a href=\"/bob\">Bob</a>\n"
+    );
+
+    // functions defined in Rust get everything in one Map
+    let mut env = Env::new();
+    env.set(
+        "Greeting",
+        Value::Fn(hatter::Fn::Native(std::rc::Rc::new(
+            |args: Args| match args.need(0)? {
+                Value::Map(props) => {
+                    let props = props.borrow();
+                    let name = props.get("name").unwrap().to_string();
+                    let body = props.get("children").unwrap().to_string();
+                    Ok(Value::Safe(format!("Hi, {}! {}", name, body).into()))
+                }
+                _ => unreachable!(),
+            },
+        ))),
+    );
+    assert_eq!(
+        "Hi, Bob! <b>Welcome</b>\n",
+        env.render("<Greeting name='Bob'> <b> Welcome").unwrap()
+    );
+
+    // capitalized tags that aren't functions are just tags
+    assert_render!("<HTML> <DIV> hi", "<HTML><DIV>hi</DIV>\n</HTML>\n");
+    assert_render!("<Nope>", "<Nope></Nope>\n");
+    assert_render!("Card := 'card'\n<Card>", "<Card></Card>\n");
+    assert!(render("def Card(title) do title\n<Card size=2>").is_err());
+    assert!(render("def Card(title) do title\n<Card> <slot> hi").is_err());
+}
+
+#[test]
+fn test_extends() {
+    let dir = std::env::temp_dir();
+    let render = |src: &str| {
+        let mut env = Env::new();
+        env.set_loader(FileLoader::new(&dir));
+        env.render(src)
+    };
+    std::fs::write(
+        dir.join("hatter-test-layout.hat"),
+        r#"<title> block title do "Default"
+<main>
+    block body
+        <p> Nothing here.
+block footer do <footer> "Bye"
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        "<title>Default</title>\n<main><p>Nothing here.</p>\n</main>\n<footer>Bye</footer>\n",
+        render("extends 'hatter-test-layout.hat'").unwrap()
+    );
+
+    assert_eq!(
+        "<title>Hi, Bob</title>\n<main><h1>Bob</h1>\n</main>\n<footer>Bye</footer>\n",
+        render(
+            r#"extends 'hatter-test-layout'
+name := "Bob"
+<p> Not printed.
+block title do "Hi, {name}"
+block body
+    <h1> name
+"#
+        )
+        .unwrap()
     );
+
+    // blocks in the child override blocks in the middle
+    std::fs::write(
+        dir.join("hatter-test-page.hat"),
+        "extends 'hatter-test-layout.hat'\nblock title do 'Page'\nblock footer do <footer> 'Page'",
+    )
+    .unwrap();
+    assert_eq!(
+        "<title>Page</title>\n<main><p>Nothing here.</p>\n</main>\n<footer>Child</footer>\n",
+        render("extends 'hatter-test-page.hat'\nblock footer do <footer> 'Child'").unwrap()
+    );
+
+    // `cycle` and `./cycle.hat` are the same template
+    std::fs::write(
+        dir.join("hatter-test-cycle.hat"),
+        "extends './hatter-test-cycle.hat'",
+    )
+    .unwrap();
+    assert!(render("extends 'hatter-test-cycle'").is_err());
+    assert!(render("extends 'hatter-test-missing.hat'").is_err());
+    assert!(render("<p> extends 'hatter-test-missing.hat'").is_err());
+}
+
+#[test]
+fn test_include() {
+    let mut loader = MapLoader::new();
+    loader.insert("nav.hat", "<nav> <a href='/'> Home");
+    loader.insert("greet.hat", "<p> \"Hi, {name}!\"");
+    loader.insert("cycle.hat", "<p> include('cycle-2')");
+    loader.insert("cycle-2.hat", "<p> include('cycle.hat')");
+    let mut env = Env::new();
+    env.set_loader(loader);
+
+    assert_eq!(
+        env.render("<header> include('nav.hat')").unwrap(),
+        "<header><nav><a href=\"/\">Home</a>\n</nav>\n</header>\n"
+    );
+    assert_eq!(
+        env.render("name := 'Bob'\ninclude('greet')").unwrap(),
+        "<p>Hi, Bob!</p>\n"
+    );
+    let err = env.render("include('cycle.hat')").unwrap_err();
+    assert_eq!(
+        err.details,
+        "include cycle: cycle.hat -> cycle-2 -> cycle.hat"
+    );
+    assert!(Env::new().render("include('missing.hat')").is_err());
+
+    // the same template, named differently
+    let mut loader = MapLoader::new();
+    loader.insert("loop.hat", "<p> include('./loop')");
+    env.set_loader(loader);
+    let err = env.render("include('loop.hat')").unwrap_err();
+    assert_eq!(err.details, "include cycle: loop.hat -> ./loop");
+
+    let mut loader = MapLoader::new();
+    loader.insert("nav.hat", "<nav> Home");
+    loader.insert("./parts/foot", "<footer> Bye");
+    env.set_loader(loader);
+    assert_eq!(
+        env.render("include('./nav')\ninclude('nav.hat')\ninclude('parts/foot.hat')")
+            .unwrap(),
+        "<nav>Home</nav>\n<nav>Home</nav>\n<footer>Bye</footer>\n"
+    );
+
+    // FileLoader stays inside its root
+    env.set_loader(FileLoader::new(std::env::temp_dir()));
+    for name in ["/etc/passwd", "../etc/passwd", "a/../../b"] {
+        let err = env.render(&format!("include('{}')", name)).unwrap_err();
+        assert!(err.details.contains("names must be relative"), "{}", name);
+    }
+}
+
+#[test]
+fn test_import() {
+    let mut loader = MapLoader::new();
+    loader.insert(
+        "helpers.hat",
+        "def shout(s) do to-uppercase(s)\ndef twice(s) do shout(s) + shout(s)\n<p> Ignored\nx := 1",
+    );
+    let mut env = Env::new();
+    env.set_loader(loader);
+
+    assert_eq!(
+        env.render("import('helpers')\n<p> twice('hi')").unwrap(),
+        "<p>HIHI</p>\n"
+    );
+    assert!(env.render("import('helpers')\nprint(x)").is_err());
+}
+
+#[test]
+fn test_env_call() {
+    let mut env = Env::new();
+    env.render("def shout(s) do to-uppercase(s) + '!'\nadd1 := fn(x) x + 1")
+        .unwrap();
+
+    assert_eq!(
+        Value::from("HI!"),
+        env.call_named("shout", vec!["hi".into()]).unwrap()
+    );
+    let add1 = env.lookup("add1").unwrap().clone();
+    assert_eq!(Value::from(3), env.call(&add1, vec![2.into()]).unwrap());
+    assert_eq!(
+        Value::from(2),
+        env.call_named("len", vec!["hi".into()]).unwrap()
+    );
+    assert_eq!(
+        Value::Bool(false),
+        env.call_named("&&", vec![true.into(), false.into()])
+            .unwrap()
+    );
+    let shout = env.lookup("shout").unwrap().clone();
+    assert_eq!(
+        map!("ok" => string!("OK!")),
+        env.call_named("try", vec![shout, "ok".into()]).unwrap()
+    );
+
+    let err = env.call_named("shout", vec![]).unwrap_err();
+    assert_eq!("expected 1 args, got 0", err.details);
+    let err = env.call_named("nope", vec![]).unwrap_err();
+    assert_eq!("can't find fn: nope", err.details);
+    assert!(env.call(&Value::from(1), vec![]).is_err());
+}
+
+#[test]
+fn test_call_args_scope() {
+    // args are evaluated in the caller's scope, not the callee's
+    assert_eval!("a := 'outer'\ndef f(a, b) do b\nf('inner', a)", "outer");
+
+    // a failing arg doesn't leave the callee's scope behind
+    let mut env = Env::new();
+    env.render("def f(a, b) do a").unwrap();
+    assert!(env.render("f(1, error('boom'))").is_err());
+    assert!(!env.var_exists("a"));
+}
+
+fn shout(args: Args) -> Result<Value> {
+    Ok(format!("{}!", args.need_string(0)?.to_uppercase()).into())
+}
+
+/// Object with a Fn field and the default `call()`.
+struct Shouter;
+
+impl Object for Shouter {
+    fn typename(&self) -> &str {
+        "Shouter"
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        match key {
+            "shout" => Some(Value::from(shout)),
+            "name" => Some("Shouter".into()),
+            _ => None,
+        }
+    }
+}
+
+/// Pretend shopping cart, for testing Objects.
+struct Cart(Vec<&'static str>);
+
+impl Object for Cart {
+    fn typename(&self) -> &str {
+        "Cart"
+    }
+
+    fn get(&self, key: &str) -> Option<Value> {
+        match key {
+            "first" => self.0.first().map(|s| (*s).into()),
+            _ => None,
+        }
+    }
+
+    fn call(&self, method: &str, args: Args) -> Result<Value> {
+        match method {
+            "item" => Ok(self.0[args.need_number(0)? as usize].into()),
+            "count" => Ok(self.0.len().into()),
+            _ => Err(hatter::Error::new(
+                hatter::ErrorKind::RuntimeError,
+                format!("no method {}", method),
+                0,
+                0,
+            )),
+        }
+    }
+
+    fn iter(&self) -> Option<Box<dyn Iterator<Item = Value> + '_>> {
+        Some(Box::new(self.0.iter().map(|s| (*s).into())))
+    }
+
+    fn to_string(&self) -> String {
+        format!("Cart({})", self.0.join(", "))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[test]
+fn test_try() {
+    assert_render!(
+        r#"
+def widget()
+    <p> "half a widget"
+    error("broken widget")
+<div>
+    try
+        widget()
+    rescue err
+        <p.error> "Failed: {err}"
+    <p> "after"
+"#,
+        "<div><p class=\"error\">Failed: broken widget</p>\n<p>after</p>\n</div>\n"
+    );
+
+    assert_render!(
+        r#"
+try
+    <p> "fine"
+rescue
+    <p> "nope"
+"#,
+        "<p>fine</p>\n"
+    );
+
+    assert_render!("try do error('quietly')\n<p> 'next'", "<p>next</p>\n");
+    assert_render!("try do error('x')\nrescue e do <b> e", "<b>x</b>\n");
+
+    assert_render!(
+        r#"
+def first-big(list)
+    for x in list
+        try
+            if x > 1
+                return x
+    0
+<b> first-big([1, 2, 3])
+"#,
+        "<b>2</b>\n"
+    );
+
+    assert_error!("try\n    x := 1\nprint(x)");
+    assert_error!("try\n    error('once')\nrescue\n    error('again')");
+}
+
+#[test]
+fn test_error_position() {
+    let src = "x := 1\n<p> x + nope";
+    let err = render(src).unwrap_err();
+    assert_eq!("can't find var: nope", err.details);
+    assert_eq!("nope", &src[err.pos..err.pos + err.len]);
+
+    let src = "<p> \"Hi, {bogus}!\"";
+    let err = render(src).unwrap_err();
+    assert_eq!("bogus", &src[err.pos..err.pos + err.len]);
+
+    let src = "x := 1\nx = 'one'";
+    let err = render(src).unwrap_err();
+    assert_eq!(7, err.pos);
+
+    let src = "len() + 1";
+    let err = render(src).unwrap_err();
+    assert_eq!("len()", &src[err.pos..err.pos + err.len]);
+}
+
+#[test]
+fn test_error_stack() {
+    let src = r#"
+def inner(x)
+    x + nope
+def outer(y)
+    inner(y)
+<p> outer(1)
+"#;
+    let err = render(src).unwrap_err();
+    assert_eq!("nope", &src[err.pos..err.pos + err.len]);
+    let stack = err
+        .stack
+        .iter()
+        .map(|f| (f.name.as_str(), &src[f.pos..f.pos + f.len]))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![("inner", "inner(y)"), ("outer", "outer(1)")], stack);
+
+    let err = render("def f()\n    return 1\nf(2)").unwrap_err();
+    assert!(err.stack.is_empty());
+}
+
+#[test]
+fn test_object() {
+    let mut env = Env::new();
+    env.set(
+        "cart",
+        Value::Object(std::rc::Rc::new(Cart(vec!["hat", "cane"]))),
+    );
+    env.set("empty", Value::Object(std::rc::Rc::new(Cart(vec![]))));
+    assert_eq!("hat", env.render("cart.first").unwrap());
+    assert_eq!("cane", env.render("cart.item(1)").unwrap());
+    assert_eq!("2", env.render("cart.count()").unwrap());
+    assert_eq!("Cart(hat, cane)", env.render("print(cart)").unwrap());
+    assert_eq!("2", env.render("len(cart)").unwrap());
+    assert_eq!("true", env.render("empty?(empty)").unwrap());
+    assert_eq!(
+        "0: hat\n1: cane\n",
+        env.render(r#"for i, item in cart do puts("{i}: {item}")"#)
+            .unwrap()
+    );
+    assert!(env.render("cart.nope()").is_err());
+    assert!(env.render("cart.first()").is_err());
+
+    // the default `call()` calls Fns from `get()`
+    env.set("o", Value::Object(std::rc::Rc::new(Shouter)));
+    assert_eq!("<p>HI!</p>\n", env.render("<p> o.shout('hi')").unwrap());
+    assert!(env.render("o.name()").is_err());
+    assert!(env.render("o.nope()").is_err());
+
+    // keyword args to Rust are one Map
+    assert_eq!("2", env.render("len(a: 1, b: 2)").unwrap());
+    assert_eq!(r#"{"s":"hi"}"#, env.render("to-json(s: 'hi')").unwrap());
+    assert!(env.render("cart.item(n: 1)").is_err());
+    assert!(env.render("o.shout(s: 'hi')").is_err());
+}
+
+#[test]
+fn test_try_from_number() {
+    use std::convert::TryFrom;
+    assert_eq!(3, usize::try_from(Value::from(3)).unwrap());
+    assert_eq!(-3, i64::try_from(Value::from(-3)).unwrap());
+    assert_eq!(1.5, f32::try_from(Value::Number(1.5)).unwrap());
+    assert!(usize::try_from(Value::from(-5)).is_err());
+    assert!(u32::try_from(Value::Number(-1.0)).is_err());
+    assert!(i64::try_from(Value::Number(1.5)).is_err());
+    assert!(i32::try_from(Value::Number(f64::NAN)).is_err());
+    assert!(i32::try_from(Value::Number(3e10)).is_err());
+    assert!(u64::try_from(Value::Number(2f64.powi(64))).is_err());
+    assert!(f64::try_from(Value::Number(f64::INFINITY)).is_err());
+    assert!(f32::try_from(Value::Number(1e300)).is_err());
+    assert!(i32::try_from(Value::from("3")).is_err());
 }
 
 #[test]
@@ -432,7 +1175,7 @@ else if i > 0
     print("Positive")
 else if i < 0
     print("Negative")
-"#;
+"#
         };
     }
 
@@ -502,25 +1245,28 @@ fn readme_features() {
     assert_render!("<b> Heya <i> there", "<b>Heya<i>there</i>\n</b>\n");
 
     // Shorthand for `id`, `class`, `type`, and `name` attributes
-    assert_render!("<div#id>", "<div id='id'></div>\n");
-    assert_render!("<div.class1.class2>", "<div class='class1 class2'></div>\n");
+    assert_render!("<div#id>", "<div id=\"id\"></div>\n");
+    assert_render!(
+        "<div.class1.class2>",
+        "<div class=\"class1 class2\"></div>\n"
+    );
     assert_render!(
         "<input@form-field-name>",
-        "<input name='form-field-name'></input>\n"
+        "<input name=\"form-field-name\">\n"
     );
-    assert_render!("<input:text>", "<input type='text'></input>\n");
+    assert_render!("<input:text>", "<input type=\"text\">\n");
 
     // Dynamic values for regular attributes
     assert_render!(
         "page := { id: 33 } <div page-num={page.id}>",
-        "<div page-num=33></div>\n"
+        "<div page-num=\"33\"></div>\n"
     );
 
     // Conditionally set attributes or enable shorthand
     assert_render!(
         "logged-in? := true
 <div .logged-in=logged-in?>",
-        "<div class='logged-in'></div>\n"
+        "<div class=\"logged-in\"></div>\n"
     );
     assert_render!(
         "is-map? := true
@@ -532,18 +1278,18 @@ fn readme_features() {
     assert_render!(
         r#"name := 'Bob'
 <span.greeting> "Hey there {name}. 2 + 2 is {2 + 2}""#,
-        "<span class='greeting'>Hey there Bob. 2 + 2 is 4</span>\n"
+        "<span class=\"greeting\">Hey there Bob. 2 + 2 is 4</span>\n"
     );
 
     // Shorthand interpolation
     assert_render!(
         r#"page := { id: 22, type: 'index', title: 'Index' }
 <span #page-{page.id} .is-{page.type}> page.title"#,
-        "<span id='page-22' class='is-index'>Index</span>\n"
+        "<span id=\"page-22\" class=\"is-index\">Index</span>\n"
     );
 
     // Implicit divs
-    assert_render!(r#"<#main>"#, "<div id='main'></div>\n");
+    assert_render!(r#"<#main>"#, "<div id=\"main\"></div>\n");
 
     // Implicit closing tags
     assert_render!(r#"<i>delicious</>"#, "<i>delicious</i>\n");
@@ -551,9 +1297,9 @@ fn readme_features() {
     // Easy inline JavaScript
     assert_render!(
         r#"<li> <a onclick=(alert("Oink!"))> "🐷""#,
-        r#"<li><a onclick='(function(e){ alert("Oink!") })(event);' href='#'>🐷</a>
+        r##"<li><a onclick="(function(e){ alert(&quot;Oink!&quot;) })(event);" href="#">🐷</a>
 </li>
-"#
+"##
     );
 
     // Basic types:
@@ -586,7 +1332,7 @@ fn readme_features() {
     assert_render!(
         r#"pages := [{id: 1, name: "Page 1"}, {id: 2, name: "2nd"}]
 <ul> for page in pages do <li id=page-{page.id}> page.name"#,
-        "<ul><li id='page-1'>Page 1</li>\n<li id='page-2'>2nd</li>\n</ul>\n"
+        "<ul><li id=\"page-1\">Page 1</li>\n<li id=\"page-2\">2nd</li>\n</ul>\n"
     );
     assert_render!(
         r#"some-map := { one: 1, two: 2 }
@@ -618,7 +1364,7 @@ name := 'Rob'"
     assert_render!(
         r#"name := "tony"
 <div.name> to-uppercase(name)"#,
-        "<div class='name'>TONY</div>\n"
+        "<div class=\"name\">TONY</div>\n"
     );
 
     // Define your own Hatter functions with strict arity and implici
@@ -1,23 +1,21 @@
 #![allow(unused)]
 #![allow(dead_code)]
-use {
-    hatter,
-    std::{
-        fs,
-        io::{self, Write},
-        path::Path,
-    },
+#![allow(clippy::assertions_on_constants)]
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
 };
 
 #[test]
 fn test_examples() -> io::Result<()> {
-    if shell("which", &["tidy"])? == "" {
+    if shell("which", &["tidy"])?.is_empty() {
         let banner = 50;
         println!("\n{}", "-".repeat(banner));
         println!("Please install tidy to run tests:\n");
         println!("$ brew install tidy");
         println!("{}\n", "-".repeat(banner));
-        return Err(io::Error::new(io::ErrorKind::Other, "tidy not found"));
+        return Err(io::Error::other("tidy not found"));
     }
     test_dir("./examples/")
 }
@@ -47,7 +45,11 @@ fn test_dir<P: AsRef<Path>>(dir: P) -> io::Result<()> {
                 }
             }
 
-            let test_path = format!("{}", path.clone().into_os_string().into_string().unwrap())
+            let test_path = path
+                .clone()
+                .into_os_string()
+                .into_string()
+                .unwrap()
                 .replace("./examples/", "./tests/examples/")
                 .replace(".hat", ".html");
 
@@ -58,7 +60,7 @@ fn test_dir<P: AsRef<Path>>(dir: P) -> io::Result<()> {
                 Err(err) => {
                     let msg = err.to_string();
                     hatter::print_error(&path, source, err);
-                    return Err(io::Error::new(io::ErrorKind::Other, msg));
+                    return Err(io::Error::other(msg));
                 }
             }
             let (expected, actual) = (pretty(&test_path)?, pretty(tmp_path)?);
@@ -88,9 +90,6 @@ fn shell(path: &str, args: &[&str]) -> io::Result<String> {
     };
     match std::str::from_utf8(&out) {
         Ok(s) => Ok(s.trim().to_string()),
-        Err(e) => Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            e.to_string(),
-        )),
+        Err(e) => Err(std::io::Error::other(e.to_string())),
     }
 }
@@ -1,4 +1,4 @@
-use hatter::{scan, Syntax::*, Token};
+use hatter::{scan, scan_all, Syntax::*, Token};
 
 macro_rules! scan {
     ($code:expr) => {
@@ -91,6 +91,12 @@ scan_test!(
 );
 scan_test!(single_quote_string, r#"'single quote'"#, String(false));
 scan_test!(escaped_single_quote_string, "'what\\'s up'", String(false));
+scan_test!(
+    escaped_backslash_string,
+    r#""C:\\" 1"#,
+    String(false),
+    Number
+);
 scan_test!(grave_string, r#"`one two three`"#, String(false));
 scan_test!(triple_string, r#" """one two three""" "#, String(false));
 scan_test!(
@@ -147,6 +153,11 @@ scan_test!(not_is_a_word, "not", Word);
 scan_test!(return_is_a_keyword, "return", Return);
 scan_test!(break_is_a_word, "break", Word);
 scan_test!(continue_is_a_word, "continue", Word);
+scan_test!(block_is_a_keyword, "block", Block);
+scan_test!(extends_is_a_keyword, "extends", Extends);
+scan_test!(try_is_a_keyword, "try", Try);
+scan_test!(try_call_is_a_word, "try(f)", Word, LParen, Word, RParen,);
+scan_test!(rescue_is_a_keyword, "rescue", Rescue);
 
 ////
 // Ops
@@ -194,6 +205,11 @@ mod skipfmt {
         LCaret, Word, Word, Equal, Word, RCaret
     );
 
+    scan_test!(angle_interpolated_map,
+        "<div class={{on: true}} id=x>",
+        LCaret, Word, Word, Equal, Word, Word, Equal, Word, RCaret
+    );
+
     scan_test!(angle_interpolated_id_full,
         "<div#{cool}>",
         LCaret, Word, Op, Word, RCaret
@@ -591,3 +607,48 @@ if true # this should work
     );
 
 }
+
+#[test]
+fn scan_all_keeps_going() {
+    let src = "<p> \"oops\n<b> 'fine'";
+    let (tokens, errors) = scan_all(src);
+    assert_eq!(1, errors.len());
+    assert_eq!("oops", &src[errors[0].pos..errors[0].pos + 4]);
+    assert_eq!(
+        vec![
+            LCaret,
+            Word,
+            RCaret,
+            String(false),
+            Semi,
+            LCaret,
+            Word,
+            RCaret,
+            String(false),
+            Semi
+        ],
+        tokens.iter().map(|t| t.kind).collect::<Vec<_>>()
+    );
+    assert_eq!("oops", tokens[3].literal());
+    assert!(scan(src).is_err());
+
+    let (_, errors) = scan_all("<ul>\n  <li> 1\n\t<li> 2\n  <li> 3\n\t<li> 4");
+    assert_eq!(2, errors.len());
+}
+
+#[test]
+fn bad_escape_codes() {
+    let src = r#""\q \u{110000} \u41 ok \n""#;
+    let (tokens, errors) = scan_all(src);
+    assert_eq!(
+        vec!["\\q", "\\u{110000}", "\\u"],
+        errors
+            .iter()
+            .map(|e| &src[e.pos..e.pos + e.len])
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![String(true), Semi],
+        tokens.iter().map(|t| t.kind).collect::<Vec<_>>()
+    );
+}
@@ -1,4 +1,6 @@
-use hatter::{compile, Stmt, Symbol, Tag};
+#![allow(clippy::approx_constant)]
+
+use hatter::{check, compile, line_and_col, Span, Stmt, Symbol, Tag};
 
 trait Intern {
     fn to_sym(&self) -> Symbol;
@@ -30,7 +32,7 @@ macro_rules! boo {
 
 macro_rules! word {
     ($code:expr) => {
-        Stmt::Word(Symbol::from($code))
+        Stmt::Word(Symbol::from($code), Span::default())
     };
 }
 
@@ -48,20 +50,69 @@ macro_rules! num {
 
 macro_rules! call {
     ($name:expr, $($arg:expr),+) => {
-        Stmt::Call(Box::new(Stmt::Word(Symbol::from($name))), vec![$($arg),+])
+        Stmt::call($name, vec![$($arg),+], Span::default())
     };
     ($name:expr) => {
-        Stmt::Call(Box::new(Stmt::Word(Symbol::from($name))), vec![])
+        Stmt::call($name, vec![], Span::default())
     };
 }
 
 macro_rules! call_expr {
     ($ex:expr, $($arg:expr),+) => {
-        Stmt::Call(Box::new($ex), vec![$($arg),+])
+        Stmt::Call(Box::new($ex), vec![$($arg),+], Span::default())
     };
     ($ex:expr) => {
-        Stmt::Call(Box::new($ex), vec![])
+        Stmt::Call(Box::new($ex), vec![], Span::default())
+    };
+}
+
+/// Reset every Span in `stmt`, so parsed nodes can be compared to the
+/// ones built by `word!` and friends.
+fn strip_spans(stmt: Stmt) -> Stmt {
+    let all = |stmts: Vec<Stmt>| stmts.into_iter().map(strip_spans).collect::<Vec<_>>();
+    let pairs = |pairs: Vec<(Symbol, Stmt)>| {
+        pairs
+            .into_iter()
+            .map(|(k, v)| (k, strip_spans(v)))
+            .collect::<Vec<_>>()
     };
+    let boxed = |stmt: Box<Stmt>| Box::new(strip_spans(*stmt));
+    match stmt {
+        Stmt::Word(name, _) => Stmt::Word(name, Span::default()),
+        Stmt::List(list) => Stmt::List(all(list)),
+        Stmt::Map(map) => Stmt::Map(pairs(map)),
+        Stmt::Call(f, args, _) => Stmt::Call(boxed(f), all(args), Span::default()),
+        Stmt::Return(val) => Stmt::Return(boxed(val)),
+        Stmt::If(conds) => Stmt::If(
+            conds
+                .into_iter()
+                .map(|(test, body)| (strip_spans(test), all(body)))
+                .collect(),
+        ),
+        Stmt::For(key, val, iter, body) => Stmt::For(key, val, boxed(iter), all(body)),
+        Stmt::While(test, body) => Stmt::While(boxed(test), all(body)),
+        Stmt::Assign(var, val, reassign, _) => {
+            Stmt::Assign(var, boxed(val), reassign, Span::default())
+        }
+        Stmt::Tag(mut tag) => {
+            tag.tag = boxed(tag.tag);
+            tag.id = boxed(tag.id);
+            tag.classes = all(tag.classes);
+            tag.attrs = tag
+                .attrs
+                .into_iter()
+                .map(|(k, v)| (strip_spans(k), strip_spans(v)))
+                .collect();
+            tag.body = all(tag.body);
+            Stmt::Tag(tag)
+        }
+        Stmt::Fn(args, body) => Stmt::Fn(args, all(body)),
+        Stmt::Args(args) => Stmt::Args(pairs(args)),
+        Stmt::Block(name, body) => Stmt::Block(name, all(body)),
+        Stmt::Extends(layout) => Stmt::Extends(boxed(layout)),
+        Stmt::Try(body, err, rescue) => Stmt::Try(all(body), err, all(rescue)),
+        stmt @ (Stmt::None | Stmt::Value(_)) => stmt,
+    }
 }
 
 fn print_nodes(i: usize, nodes: &[Stmt]) {
@@ -91,7 +142,11 @@ macro_rules! parse_test {
     ($name:ident, $code:expr, $($stmt:expr,)+) => {
         #[test]
         fn $name() {
-            let nodes = compile($code).unwrap();
+            let nodes = compile($code)
+                .unwrap()
+                .into_iter()
+                .map(strip_spans)
+                .collect::<Vec<_>>();
             let mut i = 0;
             $(
                 let node = nodes.get(i).unwrap();
@@ -199,9 +254,44 @@ parse_test!(
 parse_test!(
     escaped_single_quote_string,
     r#"'what\'s up'"#,
-    string!("what\\'s up")
+    string!("what's up")
+);
+parse_test!(
+    pipe,
+    "name | upcase | truncate(20)",
+    call_expr!(
+        word!("truncate"),
+        call_expr!(word!("upcase"), word!("name")),
+        num!(20)
+    )
+);
+parse_test!(
+    pipe_precedence,
+    "x := a + b | f",
+    Stmt::Assign(
+        "x".to_sym(),
+        bx!(call_expr!(word!("f"), call!("+", word!("a"), word!("b")))),
+        false,
+        Span::default()
+    )
+);
+parse_test!(
+    escape_codes_string,
+    r#""tab\tnew\nline \\ \"quote\" \u{1F600}\u{e9}""#,
+    string!("tab\tnew\nline \\ \"quote\" \u{1F600}\u{e9}")
+);
+parse_test!(
+    escaped_brace_string,
+    r#""{a} \{b} \\{c}""#,
+    call!("concat", word!("a"), string!(" {b} \\"), word!("c"))
+);
+parse_test!(
+    escaped_map_key,
+    r#"{ "a\tb": 1 }"#,
+    Stmt::Map(vec![("a\tb".to_sym(), num!(1))])
 );
 parse_test!(grave_string, r#"`one two three`"#, string!("one two three"));
+parse_test!(raw_grave_string, r#"`C:\new\{x}`"#, string!("C:\\new\\{x}"));
 parse_test!(
     triple_string,
     r#" """one two three""" "#,
@@ -617,7 +707,8 @@ def greet()
     Stmt::Assign(
         "greet".into(),
         bx!(Stmt::Fn(vec![], vec![call!("print", string!("HEY!!"))])),
-        false
+        false,
+        Span::default(),
     )
 );
 
@@ -636,7 +727,8 @@ def greet(name)
                 call!("concat", string!("Hi there, "), word!("name"), string!("!"))
             )]
         )),
-        false
+        false,
+        Span::default(),
     )
 );
 
@@ -662,7 +754,8 @@ def greet(title, name)
                 )
             )]
         )),
-        false
+        false,
+        Span::default(),
     )
 );
 
@@ -691,7 +784,8 @@ def greet(
                 )
             )]
         )),
-        false
+        false,
+        Span::default(),
     )
 );
 
@@ -715,7 +809,8 @@ def <<(a, b)
                 Stmt::Return(bx!(word!("a")))
             ]
         )),
-        false
+        false,
+        Span::default(),
     )
 );
 
@@ -728,7 +823,8 @@ parse_test!(
             vec!["x".into()],
             vec![Stmt::Return(bx!(call!("+", word!("x"), num!(1))))]
         )),
-        false
+        false,
+        Span::default(),
     ),
     call!("z", num!(2))
 );
@@ -745,18 +841,53 @@ z(2)"#,
             vec!["x".into()],
             vec![Stmt::Return(bx!(call!("+", word!("x"), num!(1))))]
         )),
-        false
+        false,
+        Span::default(),
     ),
     call!("z", num!(2))
 );
 
+////
+// layouts
+
+parse_test!(
+    basic_extends,
+    r#"extends "layout.hat""#,
+    Stmt::Extends(bx!(string!("layout.hat")))
+);
+
+parse_test!(
+    empty_block,
+    "block body",
+    Stmt::Block("body".into(), vec![])
+);
+
+parse_test!(
+    one_line_block,
+    r#"block title do "Welcome""#,
+    Stmt::Block("title".into(), vec![string!("Welcome")])
+);
+
+parse_test!(
+    indented_block,
+    r#"
+block body
+    <h1> title
+"#,
+    {
+        let mut h1 = tag!("h1");
+        h1.set_body(vec![word!("title")]);
+        Stmt::Block("body".into(), vec![h1.into()])
+    }
+);
+
 ////
 // assign
 
 parse_test!(
     assign,
     "a := 123",
-    Stmt::Assign("a".into(), bx!(num!(123)), false)
+    Stmt::Assign("a".into(), bx!(num!(123)), false, Span::default())
 );
 
 ////
@@ -765,7 +896,7 @@ parse_test!(
 parse_test!(
     reassign,
     "a = 123",
-    Stmt::Assign("a".into(), bx!(num!(123)), true)
+    Stmt::Assign("a".into(), bx!(num!(123)), true, Span::default())
 );
 
 ////
@@ -1126,6 +1257,22 @@ parse_test!(angle_interpolated_attr_name_full, "<div {name}=true/>", {
     Stmt::Tag(tag)
 });
 
+parse_test!(spread_attrs, "<input.big ...attrs type='text'>", {
+    let mut tag = tag!("input");
+    tag.add_class(string!("big"));
+    tag.add_spread(word!("attrs"));
+    tag.add_attr(string!("type"), string!("text"));
+    Stmt::Tag(tag)
+});
+
+parse_test!(spread_attrs_braces, "<input {attrs} disabled/>", {
+    let mut tag = tag!("input");
+    tag.add_spread(word!("attrs"));
+    tag.add_attr(string!("disabled"), boo!(true));
+    tag.close();
+    Stmt::Tag(tag)
+});
+
 parse_test!(
     angle_interpolated_attr_value_partial,
     "<div data-name=some-{thing}/>",
@@ -1269,7 +1416,7 @@ parse_test!(
         form.set_body(
             vec![h3, lbl1, lbl2, br, submit, reset]
                 .into_iter()
-                .map(|x| Stmt::Tag(x))
+                .map(Stmt::Tag)
                 .collect(),
         );
 
@@ -1407,3 +1554,44 @@ xparse_test!(
         Stmt::Tag(ul)
     }
 );
+
+#[test]
+fn check_finds_every_error() {
+    let src = r#"
+<ul>
+    <li> (1 +
+    <li> ok
+def greet(
+    name
+<p> "unclosed
+if
+<p> greet('world')
+"#;
+    let errors = check(src)
+        .iter()
+        .map(|e| (line_and_col(src, e.pos).0, e.details.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (3, "expected Atom, got Semi".to_string()),
+            (7, "expected Word, got LCaret".to_string()),
+            (
+                7,
+                "Unclosed string. Expected closing quote: \"  ".to_string()
+            ),
+            (8, "expected Atom, got Semi".to_string()),
+        ],
+        errors
+    );
+    assert!(check("<p> 'fine'").is_empty());
+}
+
+#[test]
+fn spans_are_compared() {
+    assert_eq!(compile("x").unwrap(), compile("x").unwrap());
+    assert_ne!(compile("x").unwrap(), compile(" x").unwrap());
+    assert_eq!(
+        vec![Stmt::Word("x".to_sym(), Span::new(1, 1))],
+        compile(" x").unwrap()
+    );
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "serde")]
+
+use {
+    hatter::{Env, Value},
+    serde::Serialize,
+    std::collections::BTreeMap,
+};
+
+#[derive(Serialize)]
+struct User {
+    name: String,
+    age: u32,
+    admin: bool,
+    nickname: Option<String>,
+    tags: Vec<&'static str>,
+    role: Role,
+    score: (u8, f32),
+}
+
+#[derive(Serialize)]
+enum Role {
+    Member,
+    Guest(String),
+    Mod { since: u16 },
+}
+
+fn user(role: Role) -> User {
+    User {
+        name: "Alice".into(),
+        age: 30,
+        admin: false,
+        nickname: None,
+        tags: vec!["rust", "hats"],
+        role,
+        score: (9, 0.5),
+    }
+}
+
+fn render(src: &str, val: Value) -> String {
+    let mut env = Env::new();
+    env.set("user", val);
+    env.render(src).unwrap()
+}
+
+#[test]
+fn test_from_serialize() {
+    let val = Value::from_serialize(&user(Role::Member)).unwrap();
+    assert_eq!("Alice", render("user.name", val.clone()));
+    assert_eq!("30", render("user.age", val.clone()));
+    assert_eq!("false", render("user.admin", val.clone()));
+    assert_eq!("none", render("type(user.nickname)", val.clone()));
+    assert_eq!("hats", render("user.tags[1]", val.clone()));
+    assert_eq!("Member", render("user.role", val.clone()));
+    assert_eq!("0.5", render("user.score[1]", val.clone()));
+    assert_eq!(
+        "name age admin nickname tags role score ",
+        render("for k, _ in user do print(k + ' ')", val)
+    );
+
+    let val = Value::from_serialize(&user(Role::Guest("Bob".into()))).unwrap();
+    assert_eq!("Bob", render("user.role.Guest", val));
+
+    let val = Value::from_serialize(&user(Role::Mod { since: 2020 })).unwrap();
+    assert_eq!("2020", render("user.role.Mod.since", val));
+
+    let mut map = BTreeMap::new();
+    map.insert(1, "one");
+    map.insert(2, "two");
+    let val = Value::from_serialize(&map).unwrap();
+    assert_eq!("two", render("user['2']", val));
+
+    let mut map = BTreeMap::new();
+    map.insert(vec![1], "one");
+    assert!(Value::from_serialize(&map).is_err());
+}
+
+#[test]
+fn test_serialize() {
+    let val = Value::from_serialize(&user(Role::Mod { since: 2020 })).unwrap();
+    let again = Value::from_serialize(&val).unwrap();
+    assert_eq!(val, again);
+
+    let mut env = Env::new();
+    let val = env.eval_src("[1, 'two', { three: 3.5 }, true]").unwrap();
+    assert_eq!(val, Value::from_serialize(&val).unwrap());
+
+    let val = env.eval_src("fn() 1").unwrap();
+    assert!(Value::from_serialize(&val).is_err());
+}
@@ -0,0 +1,330 @@
+use {
+    hatter::{Env, OMap, Stmt, Template, Templates},
+    std::{
+        collections::HashMap,
+        fmt, fs,
+        io::{self, Write},
+        path::PathBuf,
+        sync::Arc,
+        thread,
+        time::{Duration, SystemTime},
+    },
+};
+
+/// Fresh, empty directory for templates.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("users")).unwrap();
+    dir
+}
+
+fn is_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_send_sync() {
+    is_send_sync::<Stmt>();
+    is_send_sync::<Template>();
+    is_send_sync::<Templates>();
+}
+
+#[test]
+fn test_shared_template() {
+    let template = Arc::new(Template::from(r#"<p> "Hi, {name}!""#));
+
+    let handles = (0..4)
+        .map(|i| {
+            let template = template.clone();
+            thread::spawn(move || {
+                let mut env = Env::new();
+                env.set("name", format!("thread {}", i));
+                env.render_stmts(template.stmts().unwrap()).unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(
+            format!("<p>Hi, thread {}!</p>\n", i),
+            handle.join().unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_templates() {
+    let dir = temp_dir("hatter-test-templates");
+    fs::write(dir.join("layout.hat"), "<main> block body").unwrap();
+    fs::write(
+        dir.join("users/show.hat"),
+        "extends 'layout'\nblock body do <h1> name",
+    )
+    .unwrap();
+    fs::write(dir.join("notes.txt"), "not a template").unwrap();
+
+    let templates = Templates::new(&dir).unwrap();
+    assert_eq!(vec!["layout", "users/show"], templates.names());
+
+    let mut context = HashMap::new();
+    context.insert("name", "Bob");
+    assert_eq!(
+        "<main><h1>Bob</h1>\n</main>\n",
+        templates.render("users/show", context).unwrap()
+    );
+    assert!(templates.render("users/missing", OMap::new()).is_err());
+}
+
+#[test]
+fn test_templates_reload() {
+    let dir = temp_dir("hatter-test-templates-reload");
+    let path = dir.join("index.hat");
+    fs::write(&path, "<p> 'old'").unwrap();
+    let templates = Templates::new(&dir).unwrap();
+    assert_eq!(
+        "<p>old</p>\n",
+        templates.render("index", OMap::new()).unwrap()
+    );
+
+    fs::write(&path, "<p> 'new'").unwrap();
+    let later = SystemTime::now() + Duration::from_secs(10);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+
+    let expected = if cfg!(debug_assertions) { "new" } else { "old" };
+    assert_eq!(
+        format!("<p>{}</p>\n", expected),
+        templates.render("index", OMap::new()).unwrap()
+    );
+}
+
+#[test]
+fn test_templates_loader() {
+    let dir = temp_dir("hatter-test-templates-loader");
+    fs::write(dir.join("nav.hat"), "<nav> 'Home'").unwrap();
+    fs::write(dir.join("layout.hat"), "<main> block body").unwrap();
+    fs::write(
+        dir.join("index.hat"),
+        "extends 'layout.hat'\nblock body do include('./nav')",
+    )
+    .unwrap();
+    fs::write(dir.join("sneaky.hat"), "include('../nav')").unwrap();
+    let templates = Templates::new(&dir).unwrap();
+
+    assert!(Arc::ptr_eq(
+        &templates.get("nav").unwrap(),
+        &templates.get("./nav.hat").unwrap()
+    ));
+    assert_eq!(
+        "<main><nav>Home</nav>\n</main>\n",
+        templates.render("index", OMap::new()).unwrap()
+    );
+
+    // includes come from the cache, not the disk, in release builds
+    fs::remove_file(dir.join("nav.hat")).unwrap();
+    assert_eq!(
+        !cfg!(debug_assertions),
+        templates.render("index", OMap::new()).is_ok()
+    );
+
+    // names can't leave the directory
+    for name in ["../nav", "/etc/passwd", "users/../../nav"] {
+        let err = templates.get(name).unwrap_err();
+        assert!(err.details.contains("names must be relative"), "{}", name);
+    }
+    assert!(templates.render("sneaky", OMap::new()).is_err());
+}
+
+#[test]
+fn test_templates_errors() {
+    assert!(Templates::new("/hatter/does/not/exist").is_err());
+
+    let dir = temp_dir("hatter-test-templates-errors");
+    fs::write(dir.join("users/broken.hat"), "<p> (").unwrap();
+    let err = Templates::new(&dir).unwrap_err();
+    assert_eq!(Some("users/broken"), err.file.unwrap().name.as_deref());
+}
+
+/// Remembers each chunk of HTML written to it.
+#[derive(Default)]
+struct Chunks(Vec<String>);
+
+impl Write for Chunks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.0.push(String::from_utf8(buf.to_vec()).unwrap());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Fails every write.
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("broken pipe"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_render_to() {
+    let template = Template::from("<ul>\n  <li> 'one'\n  <li> 'two'");
+    let mut env = Env::new();
+    let mut chunks = Chunks::default();
+    template.render_to(&mut env, &mut chunks).unwrap();
+    assert_eq!(
+        vec!["<ul>", "<li>", "one", "</li>\n", "<li>", "two", "</li>\n", "</ul>\n"],
+        chunks.0
+    );
+    assert_eq!(chunks.0.concat(), template.render(&mut env).unwrap());
+
+    assert!(template.render_to(&mut env, &mut Broken).is_err());
+}
+
+#[test]
+fn test_render_to_fmt() {
+    let template = Template::from("<ul>\n  <li> '🎩'\n  <li> 'two'");
+    let mut env = Env::new();
+    let mut out = String::new();
+    template.render_to_fmt(&mut env, &mut out).unwrap();
+    assert_eq!(out, template.render(&mut env).unwrap());
+
+    // a `Display` impl can render straight into its Formatter
+    struct Page<'t>(&'t Template);
+    impl fmt::Display for Page<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0
+                .render_to_fmt(&mut Env::new(), f)
+                .map_err(|_| fmt::Error)
+        }
+    }
+    assert_eq!(out, Page(&template).to_string());
+
+    let mut out = String::new();
+    env.render_to_fmt("<b> 'hi'", &mut out).unwrap();
+    assert_eq!("<b>hi</b>\n", out);
+    assert!(env.render_to_fmt("<b> nope()", &mut out).is_err());
+}
+
+#[test]
+fn test_render_with() {
+    let template = Template::from(r#"<p> "{greeting}, {name}!""#);
+
+    let mut context = HashMap::new();
+    context.insert("greeting", "Hi");
+    context.insert("name", "Alice");
+    assert_eq!(
+        "<p>Hi, Alice!</p>\n",
+        template.render_with(&context).unwrap()
+    );
+
+    let context = vec![("greeting", "Hey"), ("name", "Bob"), ("name", "Carol")];
+    assert_eq!(
+        "<p>Hey, Carol!</p>\n",
+        template.render_with(&context).unwrap()
+    );
+
+    let mut context = OMap::new();
+    context.insert("greeting", "Hello".into());
+    context.insert("name", "<Dave>".into());
+    assert_eq!(
+        "<p>Hello, &lt;Dave&gt;!</p>\n",
+        template.render_with(context).unwrap()
+    );
+
+    assert!(template.render_with(OMap::new()).is_err());
+}
+
+#[test]
+fn test_error_includes() {
+    let dir = temp_dir("hatter-test-error-includes");
+    fs::write(dir.join("nav.hat"), "<nav>\n  <a> nope()").unwrap();
+    fs::write(dir.join("index.hat"), "<p> 'hi'\ninclude('nav')").unwrap();
+    let templates = Templates::new(&dir).unwrap();
+
+    let err = templates.render("index", OMap::new()).unwrap_err();
+    let file = err.file.as_ref().unwrap();
+    assert_eq!(Some("nav"), file.name.as_deref());
+    assert_eq!("nope", &file.code[err.pos..err.pos + err.len]);
+
+    let include = err.includes().next().unwrap();
+    let file = include.file.as_ref().unwrap();
+    assert_eq!(Some("index"), file.name.as_deref());
+    assert_eq!(
+        "include('nav')",
+        &file.code[include.pos..include.pos + include.len]
+    );
+
+    let shown = err.to_string();
+    assert!(shown.contains(" --> nav:2:7\n"), "{}", shown);
+    assert!(shown.contains("2  |   <a> nope()\n"), "{}", shown);
+    assert!(shown.contains("   |       ^^^^ "), "{}", shown);
+    assert!(
+        shown.contains("   = included from index:2:1\n"),
+        "{}",
+        shown
+    );
+}
+
+#[test]
+fn test_error_imports() {
+    let dir = temp_dir("hatter-test-error-imports");
+    fs::write(dir.join("helpers.hat"), "def boom()\n  return nope()").unwrap();
+    fs::write(
+        dir.join("index.hat"),
+        "import('helpers')\n<p> 'hi'\n<p> boom()",
+    )
+    .unwrap();
+    let templates = Templates::new(&dir).unwrap();
+
+    let err = templates.render("index", OMap::new()).unwrap_err();
+    let file = err.file.as_ref().unwrap();
+    assert_eq!(Some("helpers"), file.name.as_deref());
+    assert_eq!("nope", &file.code[err.pos..err.pos + err.len]);
+
+    let frame = &err.stack[0];
+    let file = frame.file.as_ref().unwrap();
+    assert_eq!(Some("index"), file.name.as_deref());
+    assert_eq!("boom()", &file.code[frame.pos..frame.pos + frame.len]);
+
+    let shown = err.to_string();
+    assert!(shown.contains(" --> helpers:2:10\n"), "{}", shown);
+    assert!(shown.contains("2  |   return nope()\n"), "{}", shown);
+}
+
+#[test]
+fn test_error_layouts() {
+    let dir = temp_dir("hatter-test-error-layouts");
+    fs::write(dir.join("layout.hat"), "<main>\n  block body\n  oops()").unwrap();
+    fs::write(
+        dir.join("page.hat"),
+        "extends 'layout'\nblock body do <p> 'hi'",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("child.hat"),
+        "extends 'layout'\nblock body do <p> bad()",
+    )
+    .unwrap();
+    let templates = Templates::new(&dir).unwrap();
+
+    let err = templates.render("page", OMap::new()).unwrap_err();
+    assert_eq!(Some("layout"), err.file.unwrap().name.as_deref());
+
+    let err = templates.render("child", OMap::new()).unwrap_err();
+    let file = err.file.unwrap();
+    assert_eq!(Some("child"), file.name.as_deref());
+    assert_eq!("bad", &file.code[err.pos..err.pos + err.len]);
+}